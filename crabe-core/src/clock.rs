@@ -0,0 +1,49 @@
+use std::sync::Mutex;
+
+use chrono::{DateTime, Duration, Utc};
+
+/// The source of the current time, which can be replaced to control time in tests.
+pub trait Clock: Send + Sync {
+    fn now(&self) -> DateTime<Utc>;
+}
+
+/// The clock of the system.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// A clock that stands still until it is moved explicitly.
+#[derive(Debug)]
+pub struct MockClock {
+    now: Mutex<DateTime<Utc>>,
+}
+
+impl MockClock {
+    pub fn new(now: DateTime<Utc>) -> Self {
+        Self {
+            now: Mutex::new(now),
+        }
+    }
+
+    pub fn set(&self, now: DateTime<Utc>) {
+        *self.now.lock().expect("The mock clock mutex was poisoned.") = now;
+    }
+
+    /// Moves the clock forward by the given duration, returning the new time.
+    pub fn advance(&self, duration: Duration) -> DateTime<Utc> {
+        let mut now = self.now.lock().expect("The mock clock mutex was poisoned.");
+        *now += duration;
+        *now
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> DateTime<Utc> {
+        *self.now.lock().expect("The mock clock mutex was poisoned.")
+    }
+}
@@ -0,0 +1,154 @@
+use std::ops::Range;
+
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+
+/// How the pattern of a [`KeywordRule`] has to appear in a message.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchMode {
+    /// The pattern has to be surrounded by word boundaries, so `rust` doesn't match `rusty`.
+    #[default]
+    WholeWord,
+    /// The pattern may appear anywhere, so `rust` matches `rustacean`.
+    Substring,
+}
+
+/// A case-insensitive regular expression either counting as or excluding a mention of Rust.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct KeywordRule {
+    pub pattern: String,
+    #[serde(default)]
+    pub mode: MatchMode,
+}
+
+impl KeywordRule {
+    pub fn new(pattern: impl Into<String>, mode: MatchMode) -> Self {
+        Self {
+            pattern: pattern.into(),
+            mode,
+        }
+    }
+
+    fn compile(&self) -> Result<Regex, String> {
+        if self.pattern.trim().is_empty() {
+            return Err("Keyword patterns must not be empty.".to_string());
+        }
+
+        let bare = format!("(?:{})", self.pattern);
+        let pattern = match self.mode {
+            MatchMode::WholeWord => format!(r"\b{}\b", bare),
+            MatchMode::Substring => bare.clone(),
+        };
+        let regex = RegexBuilder::new(&pattern)
+            .case_insensitive(true)
+            .build()
+            .map_err(|e| format!("`{}` is not a valid pattern: {}", self.pattern, e))?;
+
+        // A pattern matching nothing at all would count every message. Whole-word rules never
+        // match an empty text because of the word boundaries, so the bare pattern is checked.
+        if Regex::new(&bare).is_ok_and(|bare| bare.is_match("")) {
+            return Err(format!(
+                "`{}` matches an empty text, so every message would count.",
+                self.pattern
+            ));
+        }
+        Ok(regex)
+    }
+}
+
+/// The rules deciding whether a message mentions Rust.
+///
+/// A message mentions Rust if any of the included patterns matches a part of it that isn't also
+/// matched by one of the excluded patterns, e.g. excluding `rust belt` still counts the second
+/// word of "rust belt or rust?".
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct KeywordRules {
+    pub include: Vec<KeywordRule>,
+    pub exclude: Vec<KeywordRule>,
+}
+
+impl Default for KeywordRules {
+    fn default() -> Self {
+        Self {
+            include: vec![KeywordRule::new("rust", MatchMode::WholeWord)],
+            exclude: Vec::new(),
+        }
+    }
+}
+
+impl KeywordRules {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.include.is_empty() {
+            return Err("At least one keyword has to be included.".to_string());
+        }
+        Detector::new(self).map(|_| ())
+    }
+}
+
+/// A match of an included keyword in a message.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Detection {
+    /// The pattern of the rule that matched.
+    pub rule: String,
+    /// The text that was matched.
+    pub matched: String,
+}
+
+/// The compiled form of [`KeywordRules`].
+pub struct Detector {
+    rules: KeywordRules,
+    include: Vec<(String, Regex)>,
+    exclude: Vec<Regex>,
+}
+
+impl Detector {
+    pub fn new(rules: &KeywordRules) -> Result<Self, String> {
+        let include = rules
+            .include
+            .iter()
+            .map(|rule| Ok((rule.pattern.clone(), rule.compile()?)))
+            .collect::<Result<_, String>>()?;
+        let exclude = rules
+            .exclude
+            .iter()
+            .map(KeywordRule::compile)
+            .collect::<Result<_, String>>()?;
+
+        Ok(Self {
+            rules: rules.clone(),
+            include,
+            exclude,
+        })
+    }
+
+    /// The rules this detector was compiled from.
+    pub fn rules(&self) -> &KeywordRules {
+        &self.rules
+    }
+
+    /// Finds the first mention of Rust in the given text, if there is any.
+    pub fn detect(&self, text: &str) -> Option<Detection> {
+        let excluded = self
+            .exclude
+            .iter()
+            .flat_map(|regex| regex.find_iter(text).map(|m| m.range()))
+            .collect::<Vec<_>>();
+        let overlaps = |range: &Range<usize>| {
+            excluded
+                .iter()
+                .any(|excluded| range.start < excluded.end && excluded.start < range.end)
+        };
+
+        self.include.iter().find_map(|(rule, regex)| {
+            regex
+                .find_iter(text)
+                .find(|m| !overlaps(&m.range()))
+                .map(|m| Detection {
+                    rule: rule.clone(),
+                    matched: m.as_str().to_string(),
+                })
+        })
+    }
+}
@@ -0,0 +1,144 @@
+use std::time::Duration;
+
+/// A unit durations are expressed in, ordered from the largest to the smallest.
+///
+/// Months and years have a fixed length of 30 and 365 days, as the durations aren't tied to a
+/// calendar.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Unit {
+    Year,
+    Month,
+    Week,
+    Day,
+    Hour,
+    Minute,
+    Second,
+}
+
+impl Unit {
+    pub const ALL: [Unit; 7] = [
+        Unit::Year,
+        Unit::Month,
+        Unit::Week,
+        Unit::Day,
+        Unit::Hour,
+        Unit::Minute,
+        Unit::Second,
+    ];
+
+    pub fn seconds(self) -> u64 {
+        match self {
+            Unit::Year => 365 * 24 * 60 * 60,
+            Unit::Month => 30 * 24 * 60 * 60,
+            Unit::Week => 7 * 24 * 60 * 60,
+            Unit::Day => 24 * 60 * 60,
+            Unit::Hour => 60 * 60,
+            Unit::Minute => 60,
+            Unit::Second => 1,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Unit::Year => "year",
+            Unit::Month => "month",
+            Unit::Week => "week",
+            Unit::Day => "day",
+            Unit::Hour => "hour",
+            Unit::Minute => "minute",
+            Unit::Second => "second",
+        }
+    }
+
+    /// Formats an amount of this unit, e.g. `1 day` or `3 days`.
+    pub fn format(self, amount: u64) -> String {
+        format!(
+            "{} {}{}",
+            amount,
+            self.name(),
+            if amount == 1 { "" } else { "s" }
+        )
+    }
+}
+
+/// Formats durations as human readable text such as `2 weeks and 3 days`.
+///
+/// A duration is expressed in up to `precision` consecutive units, starting at the largest unit
+/// it fills at least once. Anything smaller is truncated rather than rounded, so a streak is
+/// never shown to last longer than it did, and units that are zero are left out.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Humanizer {
+    precision: usize,
+    largest: Unit,
+    smallest: Unit,
+}
+
+impl Default for Humanizer {
+    fn default() -> Self {
+        Self {
+            precision: 2,
+            largest: Unit::Year,
+            smallest: Unit::Second,
+        }
+    }
+}
+
+impl Humanizer {
+    /// Uses up to `precision` units, but always at least one.
+    pub fn with_precision(mut self, precision: usize) -> Self {
+        self.precision = precision.max(1);
+        self
+    }
+
+    /// Expresses durations in no unit larger than `largest`, e.g. `5 weeks` instead of
+    /// `1 month and 5 days` with [`Unit::Week`].
+    pub fn with_largest_unit(mut self, largest: Unit) -> Self {
+        self.largest = largest;
+        self.smallest = self.smallest.max(largest);
+        self
+    }
+
+    /// Expresses durations in no unit smaller than `smallest`.
+    pub fn with_smallest_unit(mut self, smallest: Unit) -> Self {
+        self.smallest = smallest;
+        self.largest = self.largest.min(smallest);
+        self
+    }
+
+    pub fn humanize(&self, duration: Duration) -> String {
+        let units = Unit::ALL
+            .into_iter()
+            .filter(|unit| (self.largest..=self.smallest).contains(unit));
+
+        let mut remaining = duration.as_secs();
+        let mut parts = Vec::new();
+        let mut used = 0;
+        for unit in units {
+            if used == self.precision {
+                break;
+            }
+
+            let amount = remaining / unit.seconds();
+            remaining %= unit.seconds();
+            if amount > 0 {
+                parts.push(unit.format(amount));
+            }
+            // Counting starts at the first unit the duration fills, so the precision isn't spent
+            // on leading zeros.
+            if amount > 0 || used > 0 {
+                used += 1;
+            }
+        }
+
+        match parts.split_last() {
+            None => self.smallest.format(0),
+            Some((last, [])) => last.clone(),
+            Some((last, rest)) => format!("{} and {}", rest.join(", "), last),
+        }
+    }
+}
+
+/// Formats a duration with the default [`Humanizer`], using its two most significant units.
+pub fn humanize(duration: Duration) -> String {
+    Humanizer::default().humanize(duration)
+}
@@ -0,0 +1,92 @@
+use std::{collections::HashMap, hash::Hash};
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate, TimeZone, Utc};
+
+/// The number of entries shown on a single page of the leaderboard.
+pub const PAGE_SIZE: usize = 10;
+
+/// The period of time the mentions on a leaderboard are counted in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Window {
+    AllTime,
+    Month,
+    Week,
+    Custom {
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    },
+}
+
+impl Window {
+    /// Resolves the window into a half-open range of timestamps, or `None` for all-time.
+    pub fn bounds(&self, now: DateTime<Utc>) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+        match *self {
+            Window::AllTime => None,
+            Window::Month => {
+                let start = now
+                    .date_naive()
+                    .with_day(1)
+                    .expect("Every month has a first day.");
+                Some((start_of_day(start), now))
+            }
+            Window::Week => {
+                let start =
+                    now.date_naive() - Duration::days(now.weekday().num_days_from_monday() as i64);
+                Some((start_of_day(start), now))
+            }
+            Window::Custom { from, to } => Some((from, to)),
+        }
+    }
+
+    /// Creates a custom window from two inclusive calendar dates formatted as `YYYY-MM-DD`.
+    pub fn custom(from: &str, to: &str) -> Result<Self, String> {
+        let parse = |date: &str| {
+            NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d")
+                .map_err(|_| format!("`{}` is not a valid date, please use YYYY-MM-DD.", date))
+        };
+        let (from, to) = (parse(from)?, parse(to)?);
+        if from > to {
+            return Err("The start date has to be before the end date.".to_string());
+        }
+
+        Ok(Window::Custom {
+            from: start_of_day(from),
+            to: start_of_day(to + Duration::days(1)),
+        })
+    }
+
+    pub fn title(&self) -> String {
+        match self {
+            Window::AllTime => "All time".to_string(),
+            Window::Month => "This month".to_string(),
+            Window::Week => "This week".to_string(),
+            Window::Custom { from, to } => format!(
+                "{} to {}",
+                from.format("%Y-%m-%d"),
+                (*to - Duration::days(1)).format("%Y-%m-%d")
+            ),
+        }
+    }
+}
+
+fn start_of_day(date: NaiveDate) -> DateTime<Utc> {
+    Utc.from_utc_datetime(
+        &date
+            .and_hms_opt(0, 0, 0)
+            .expect("Midnight is a valid time."),
+    )
+}
+
+/// Sorts the mention counts of each user from the most to the least mentions.
+pub fn rank<U: Ord + Hash>(counts: HashMap<U, usize>) -> Vec<(U, usize)> {
+    let mut ranking = counts.into_iter().collect::<Vec<_>>();
+    ranking.sort_by(|(a_user, a_count), (b_user, b_count)| {
+        b_count.cmp(a_count).then(a_user.cmp(b_user))
+    });
+    ranking
+}
+
+/// The number of pages needed to show the whole ranking, which is at least one.
+pub fn page_count(entries: usize) -> usize {
+    entries.div_ceil(PAGE_SIZE).max(1)
+}
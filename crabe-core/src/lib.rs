@@ -0,0 +1,14 @@
+//! The domain of the bot, independent of Discord: detecting mentions of Rust in messages, tracking
+//! the streaks without any and ranking who mentioned Rust the most.
+
+pub mod clock;
+pub mod detection;
+pub mod humanize;
+pub mod leaderboard;
+pub mod markdown;
+pub mod normalize;
+pub mod record;
+pub mod schedule;
+pub mod scheduler;
+pub mod sources;
+pub mod tracker;
@@ -0,0 +1,227 @@
+use std::ops::Range;
+
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+/// The kinds of Discord markdown a part of a message can be wrapped in.
+///
+/// Kinds can be nested, e.g. a spoiler inside of a block quote is both a [`Kinds::QUOTE`] and a
+/// [`Kinds::SPOILER`], while text without any markdown has no kinds at all.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Kinds(u8);
+
+impl Kinds {
+    pub const QUOTE: Kinds = Kinds(1 << 0);
+    pub const CODE_BLOCK: Kinds = Kinds(1 << 1);
+    pub const INLINE_CODE: Kinds = Kinds(1 << 2);
+    pub const SPOILER: Kinds = Kinds(1 << 3);
+    pub const LINK: Kinds = Kinds(1 << 4);
+
+    const CODE: Kinds = Kinds(Self::CODE_BLOCK.0 | Self::INLINE_CODE.0);
+
+    pub fn is_plain(self) -> bool {
+        self.0 == 0
+    }
+
+    /// Whether any of the kinds of `other` are also in these.
+    pub fn intersects(self, other: Kinds) -> bool {
+        self.0 & other.0 != 0
+    }
+
+    fn insert(&mut self, other: Kinds) {
+        self.0 |= other.0;
+    }
+}
+
+/// A contiguous part of a message wrapped in the same kinds of markdown.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Segment<'a> {
+    pub text: &'a str,
+    pub kinds: Kinds,
+}
+
+/// Which parts of a message are searched for mentions of Rust.
+///
+/// Parts wrapped in several kinds of markdown are only searched if all of them are enabled.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct Regions {
+    pub plain: bool,
+    pub quotes: bool,
+    pub code_blocks: bool,
+    pub inline_code: bool,
+    pub spoilers: bool,
+    pub links: bool,
+}
+
+impl Default for Regions {
+    fn default() -> Self {
+        Self {
+            plain: true,
+            quotes: false,
+            code_blocks: false,
+            inline_code: true,
+            spoilers: true,
+            links: false,
+        }
+    }
+}
+
+impl Regions {
+    fn allows(&self, kinds: Kinds) -> bool {
+        if kinds.is_plain() {
+            return self.plain;
+        }
+
+        [
+            (Kinds::QUOTE, self.quotes),
+            (Kinds::CODE_BLOCK, self.code_blocks),
+            (Kinds::INLINE_CODE, self.inline_code),
+            (Kinds::SPOILER, self.spoilers),
+            (Kinds::LINK, self.links),
+        ]
+        .iter()
+        .all(|&(kind, enabled)| enabled || !kinds.intersects(kind))
+    }
+
+    /// Returns the text of the enabled regions, replacing every other region with a space so
+    /// that words on either side of it aren't joined together.
+    pub fn filter(&self, content: &str) -> String {
+        parse(content)
+            .into_iter()
+            .map(|segment| {
+                if self.allows(segment.kinds) {
+                    segment.text
+                } else {
+                    " "
+                }
+            })
+            .collect()
+    }
+}
+
+/// Splits a message into segments by the markdown they are wrapped in.
+pub fn parse(content: &str) -> Vec<Segment<'_>> {
+    let mut kinds = vec![Kinds::default(); content.len()];
+
+    mark_code_blocks(content, &mut kinds);
+    mark_inline_code(content, &mut kinds);
+    mark_quotes(content, &mut kinds);
+    mark_spoilers(content, &mut kinds);
+    mark_links(content, &mut kinds);
+
+    let mut segments = Vec::new();
+    let mut start = 0;
+    for end in 1..=content.len() {
+        if end == content.len() || (kinds[end] != kinds[start] && content.is_char_boundary(end)) {
+            segments.push(Segment {
+                text: &content[start..end],
+                kinds: kinds[start],
+            });
+            start = end;
+        }
+    }
+    segments
+}
+
+fn mark(kinds: &mut [Kinds], range: Range<usize>, kind: Kinds) {
+    for k in &mut kinds[range] {
+        k.insert(kind);
+    }
+}
+
+fn mark_code_blocks(content: &str, kinds: &mut [Kinds]) {
+    let mut offset = 0;
+    while let Some(start) = content[offset..].find("```").map(|i| offset + i) {
+        match content[start + 3..].find("```") {
+            Some(length) => {
+                let end = start + 3 + length + 3;
+                mark(kinds, start..end, Kinds::CODE_BLOCK);
+                offset = end;
+            }
+            None => break,
+        }
+    }
+}
+
+fn mark_inline_code(content: &str, kinds: &mut [Kinds]) {
+    let bytes = content.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] != b'`' || kinds[i].intersects(Kinds::CODE_BLOCK) {
+            i += 1;
+            continue;
+        }
+
+        let delimiter = if bytes.get(i + 1) == Some(&b'`') {
+            "``"
+        } else {
+            "`"
+        };
+        let start = i + delimiter.len();
+        let end = content[start..]
+            .find(delimiter)
+            .map(|length| start + length)
+            .filter(|&end| {
+                end > start && !kinds[start..end].iter().any(|k| k.intersects(Kinds::CODE))
+            });
+
+        match end {
+            Some(end) => {
+                mark(kinds, i..end + delimiter.len(), Kinds::INLINE_CODE);
+                i = end + delimiter.len();
+            }
+            None => i = start,
+        }
+    }
+}
+
+fn mark_quotes(content: &str, kinds: &mut [Kinds]) {
+    let mut line_start = 0;
+    for line in content.split_inclusive('\n') {
+        let line_end = line_start + line.len();
+        if !kinds[line_start].intersects(Kinds::CODE) {
+            // A line starting with `>>> ` turns the rest of the message into a block quote.
+            if line.starts_with(">>> ") {
+                mark(kinds, line_start..content.len(), Kinds::QUOTE);
+                return;
+            }
+            if line.starts_with("> ") {
+                mark(kinds, line_start..line_end, Kinds::QUOTE);
+            }
+        }
+        line_start = line_end;
+    }
+}
+
+fn mark_spoilers(content: &str, kinds: &mut [Kinds]) {
+    let delimiters = content
+        .match_indices("||")
+        .map(|(i, _)| i)
+        .filter(|&i| !kinds[i].intersects(Kinds::CODE))
+        .collect::<Vec<_>>();
+
+    let pairs = delimiters
+        .chunks_exact(2)
+        .map(|pair| pair[0]..pair[1] + 2)
+        .collect::<Vec<_>>();
+
+    for range in pairs {
+        mark(kinds, range, Kinds::SPOILER);
+    }
+}
+
+fn mark_links(content: &str, kinds: &mut [Kinds]) {
+    // Bare domains aren't links, as they can't be told apart from words missing a space after a
+    // period or from file names, e.g. `rust.Anyway` or `main.rs`.
+    lazy_static! {
+        static ref URL: Regex = Regex::new(r"(?i)\b(?:https?://|www\.)[^\s<>()]+").unwrap();
+    }
+
+    for url in URL.find_iter(content) {
+        if !kinds[url.start()].intersects(Kinds::CODE) {
+            mark(kinds, url.range(), Kinds::LINK);
+        }
+    }
+}
@@ -0,0 +1,222 @@
+use lazy_static::lazy_static;
+use regex::{Captures, Regex};
+use serde::{Deserialize, Serialize};
+use unicode_normalization::{char::is_combining_mark, UnicodeNormalization};
+
+/// The normalization layers applied to a message before searching it for mentions of Rust, which
+/// undo common tricks to evade the detection.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct Normalization {
+    /// Applies Unicode NFKC normalization, turning e.g. fullwidth `ｒｕｓｔ` into `rust`.
+    pub nfkc: bool,
+    /// Removes zero-width and other invisible formatting characters, e.g. in `r\u{200b}ust`.
+    pub strip_invisible: bool,
+    /// Replaces characters looking like Latin letters, e.g. the Armenian `ս` in `Rսst`, and
+    /// strips diacritics.
+    pub confusables: bool,
+    /// Joins runs of at least four single letters, e.g. `r u s t` or `r.u.s.t`. Shorter runs and
+    /// digits are left alone, so neither `I am a b c` nor `x 1 2 3 y` become a word.
+    pub collapse_spacing: bool,
+    /// Replaces digits and symbols used as letters in words, e.g. `ru5t`.
+    pub leetspeak: bool,
+}
+
+impl Default for Normalization {
+    fn default() -> Self {
+        Self {
+            nfkc: true,
+            strip_invisible: true,
+            confusables: true,
+            collapse_spacing: false,
+            leetspeak: false,
+        }
+    }
+}
+
+impl Normalization {
+    pub fn normalize(&self, text: &str) -> String {
+        let mut text = text.to_string();
+
+        if self.nfkc {
+            text = text.nfkc().collect();
+        }
+        if self.strip_invisible {
+            text.retain(|c| !is_invisible(c));
+        }
+        if self.confusables {
+            text = text
+                .chars()
+                .map(unconfuse)
+                .collect::<String>()
+                .nfd()
+                .filter(|&c| !is_combining_mark(c))
+                .nfc()
+                .collect();
+        }
+        if self.collapse_spacing {
+            text = collapse_spacing(&text);
+        }
+        // Leetspeak comes last, so digits that were never joined to letters stay digits.
+        if self.leetspeak {
+            text = replace_leetspeak(&text);
+        }
+
+        text
+    }
+}
+
+fn is_invisible(c: char) -> bool {
+    matches!(
+        c,
+        '\u{00ad}'
+            | '\u{034f}'
+            | '\u{061c}'
+            | '\u{115f}'
+            | '\u{1160}'
+            | '\u{17b4}'
+            | '\u{17b5}'
+            | '\u{180b}'..='\u{180f}'
+            | '\u{200b}'..='\u{200f}'
+            | '\u{202a}'..='\u{202e}'
+            | '\u{2060}'..='\u{206f}'
+            | '\u{3164}'
+            | '\u{fe00}'..='\u{fe0f}'
+            | '\u{feff}'
+            | '\u{ffa0}'
+            | '\u{1d173}'..='\u{1d17a}'
+            | '\u{e0000}'..='\u{e007f}'
+    )
+}
+
+/// Maps a character to the Latin letter it is commonly mistaken for.
+fn unconfuse(c: char) -> char {
+    match c {
+        // Cyrillic
+        'а' => 'a',
+        'ь' => 'b',
+        'с' | 'ϲ' => 'c',
+        'ԁ' => 'd',
+        'е' | 'ё' => 'e',
+        'һ' => 'h',
+        'і' | 'ї' => 'i',
+        'ј' => 'j',
+        'к' => 'k',
+        'м' => 'm',
+        'п' => 'n',
+        'о' => 'o',
+        'р' => 'p',
+        'г' => 'r',
+        'ѕ' => 's',
+        'т' => 't',
+        'ѵ' => 'v',
+        'ш' | 'ԝ' => 'w',
+        'х' => 'x',
+        'у' => 'y',
+        'А' => 'A',
+        'В' => 'B',
+        'С' => 'C',
+        'Е' => 'E',
+        'Н' => 'H',
+        'І' => 'I',
+        'Ј' => 'J',
+        'К' => 'K',
+        'М' => 'M',
+        'О' => 'O',
+        'Р' => 'P',
+        'Ѕ' => 'S',
+        'Т' => 'T',
+        'Х' => 'X',
+        'У' => 'Y',
+        // Greek
+        'α' => 'a',
+        'ε' => 'e',
+        'ι' => 'i',
+        'κ' => 'k',
+        'ν' => 'v',
+        'ο' => 'o',
+        'ρ' => 'p',
+        'τ' => 't',
+        'υ' => 'u',
+        'χ' => 'x',
+        'Α' => 'A',
+        'Β' => 'B',
+        'Ε' => 'E',
+        'Η' => 'H',
+        'Ι' => 'I',
+        'Κ' => 'K',
+        'Μ' => 'M',
+        'Ν' => 'N',
+        'Ο' => 'O',
+        'Ρ' => 'P',
+        'Τ' => 'T',
+        'Υ' => 'Y',
+        'Χ' => 'X',
+        'Ζ' => 'Z',
+        // Armenian
+        'ա' => 'w',
+        'հ' => 'h',
+        'ո' => 'n',
+        'ս' => 'u',
+        'ց' => 'g',
+        'օ' => 'o',
+        'Ս' => 'U',
+        'Օ' => 'O',
+        // Latin small capitals and other lookalikes
+        'ʀ' | 'ᴙ' => 'r',
+        'ᴜ' => 'u',
+        'ꜱ' => 's',
+        'ᴛ' => 't',
+        'ı' => 'i',
+        'ȷ' => 'j',
+        'ſ' => 's',
+        'Ʀ' => 'R',
+        // Cherokee
+        'Ꭱ' => 'R',
+        'Ꮪ' => 'S',
+        'Ꭲ' => 'T',
+        _ => c,
+    }
+}
+
+fn collapse_spacing(text: &str) -> String {
+    lazy_static! {
+        static ref SPACED: Regex = Regex::new(r"\b\pL\b(?:[\s.\-_*/|]+\b\pL\b){3,}").unwrap();
+        static ref SEPARATORS: Regex = Regex::new(r"[\s.\-_*/|]+").unwrap();
+    }
+
+    SPACED
+        .replace_all(text, |captures: &Captures| {
+            SEPARATORS.replace_all(&captures[0], "").into_owned()
+        })
+        .into_owned()
+}
+
+fn replace_leetspeak(text: &str) -> String {
+    lazy_static! {
+        static ref WORD: Regex = Regex::new(r"[\w@$]+").unwrap();
+    }
+
+    WORD.replace_all(text, |captures: &Captures| {
+        let word = &captures[0];
+        // Only words that also contain letters are rewritten, leaving plain numbers alone.
+        if !word.chars().any(char::is_alphabetic) {
+            return word.to_string();
+        }
+
+        word.chars()
+            .map(|c| match c {
+                '0' => 'o',
+                '1' => 'i',
+                '3' => 'e',
+                '4' | '@' => 'a',
+                '5' | '$' => 's',
+                '7' => 't',
+                '8' => 'b',
+                '9' => 'g',
+                _ => c,
+            })
+            .collect()
+    })
+    .into_owned()
+}
@@ -0,0 +1,58 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+
+/// The longest stretch of time without any mention of Rust, and when the ongoing one started.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Record {
+    pub last_mention: Option<DateTime<Utc>>,
+    pub duration: Option<Duration>,
+}
+
+impl Record {
+    /// Ends the ongoing streak with a mention at `at`, returning the streak if it beat the record.
+    pub fn end_streak(&mut self, at: DateTime<Utc>) -> Option<Duration> {
+        let streak = self
+            .last_mention
+            .and_then(|last_mention| (at - last_mention).to_std().ok());
+        let beaten = match (streak, self.duration) {
+            (Some(current), Some(previous)) if current > previous => Some(current),
+            // The record was cleared while a streak was ongoing.
+            (Some(current), None) if !current.is_zero() => Some(current),
+            _ => None,
+        };
+        if beaten.is_some() {
+            self.duration = beaten;
+        }
+
+        // Events can arrive out of order, so never move the last mention back in time.
+        self.last_mention = Some(
+            self.last_mention
+                .map_or(at, |last_mention| last_mention.max(at)),
+        );
+        if self.duration.is_none() {
+            self.duration = Some(Duration::from_secs(0));
+        }
+
+        beaten
+    }
+
+    /// The ongoing streak at `now` together with the record, if the streak already beat a record
+    /// longer than zero or one that was cleared, which has no duration.
+    pub fn ongoing_streak_beating(
+        &self,
+        now: DateTime<Utc>,
+    ) -> Option<(Duration, Option<Duration>)> {
+        let (last_mention, previous) = match (self.last_mention, self.duration) {
+            (Some(last_mention), Some(previous)) if !previous.is_zero() => {
+                (last_mention, Some(previous))
+            }
+            (Some(last_mention), None) => (last_mention, None),
+            _ => return None,
+        };
+        match (now - last_mention).to_std() {
+            Ok(current) if current > previous.unwrap_or_default() => Some((current, previous)),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,45 @@
+use std::str::FromStr;
+
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use cron::Schedule;
+
+/// A cron schedule evaluated in a specific time zone.
+pub struct ReportSchedule {
+    schedule: Schedule,
+    timezone: Tz,
+}
+
+impl ReportSchedule {
+    /// Parses a cron expression with either five fields (starting with the minute) or six to
+    /// seven fields (starting with the second) together with an IANA time zone name.
+    pub fn new(expression: &str, timezone: &str) -> Result<Self, String> {
+        let expression = expression.trim();
+        let expression = if expression.split_whitespace().count() == 5 {
+            format!("0 {}", expression)
+        } else {
+            expression.to_string()
+        };
+
+        let schedule = Schedule::from_str(&expression)
+            .map_err(|e| format!("`{}` is not a valid cron expression: {}", expression, e))?;
+        let timezone = timezone
+            .parse::<Tz>()
+            .map_err(|_| format!("`{}` is not a known time zone.", timezone))?;
+
+        Ok(Self { schedule, timezone })
+    }
+
+    /// The first time the schedule fires strictly after the given time.
+    pub fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        self.schedule
+            .after(&after.with_timezone(&self.timezone))
+            .next()
+            .map(|next| next.with_timezone(&Utc))
+    }
+
+    /// Whether a report is due at `now` after the last one was posted at `last_report`.
+    pub fn is_due(&self, last_report: DateTime<Utc>, now: DateTime<Utc>) -> bool {
+        matches!(self.next_after(last_report), Some(next) if next <= now)
+    }
+}
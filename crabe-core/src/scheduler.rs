@@ -0,0 +1,116 @@
+use std::{error::Error, sync::Arc, time::Duration};
+
+use chrono::{DateTime, Utc};
+
+use crate::clock::Clock;
+
+/// How often the scheduler checks whether any of its jobs are due by default.
+pub const TICK_INTERVAL: Duration = Duration::from_secs(15);
+
+/// The error of a [`JobRuns`] implementation.
+pub type JobRunsError = Box<dyn Error + Send + Sync>;
+
+/// A periodic piece of work owned by the [`Scheduler`].
+#[async_trait::async_trait]
+pub trait Job: Send + Sync {
+    /// A unique name of the job, used to persist when it last ran.
+    fn name(&self) -> &'static str;
+
+    /// The time the job is due again after it last ran at `last_run`.
+    fn next_run(&self, last_run: DateTime<Utc>) -> DateTime<Utc>;
+
+    async fn run(&self, now: DateTime<Utc>);
+}
+
+/// Where the [`Scheduler`] persists when each of its jobs last ran.
+pub trait JobRuns: Send + Sync {
+    fn load_job_run(&self, name: &str) -> Result<Option<DateTime<Utc>>, JobRunsError>;
+    fn save_job_run(&self, name: &str, last_run: DateTime<Utc>) -> Result<(), JobRunsError>;
+}
+
+/// Runs periodic jobs independently of any events received from Discord.
+///
+/// The time a job last ran is persisted, so runs missed while the bot was offline are caught up
+/// on with a single run as soon as it is back. The current time is passed into [`Scheduler::tick`]
+/// instead of being read inside, which allows driving the scheduler with a fake clock.
+pub struct Scheduler {
+    runs: Arc<dyn JobRuns>,
+    clock: Arc<dyn Clock>,
+    jobs: Vec<Box<dyn Job>>,
+    tick_interval: Duration,
+}
+
+impl Scheduler {
+    pub fn new(runs: Arc<dyn JobRuns>, clock: Arc<dyn Clock>) -> Self {
+        Self {
+            runs,
+            clock,
+            jobs: Vec::new(),
+            tick_interval: TICK_INTERVAL,
+        }
+    }
+
+    pub fn with_job(mut self, job: impl Job + 'static) -> Self {
+        self.jobs.push(Box::new(job));
+        self
+    }
+
+    /// Checks whether any jobs are due more or less often than every [`TICK_INTERVAL`], e.g. to
+    /// keep tests driving the scheduler with a fake clock fast.
+    pub fn with_tick_interval(mut self, tick_interval: Duration) -> Self {
+        self.tick_interval = tick_interval;
+        self
+    }
+
+    /// Runs every job that is due at `now`.
+    pub async fn tick(&self, now: DateTime<Utc>) {
+        for job in &self.jobs {
+            let last_run = match self.runs.load_job_run(job.name()) {
+                Ok(last_run) => last_run,
+                Err(e) => {
+                    tracing::error!(
+                        "An error occurred loading the last run of {}: {}",
+                        job.name(),
+                        e
+                    );
+                    continue;
+                }
+            };
+
+            if let Some(last_run) = last_run {
+                let next_run = job.next_run(last_run);
+                if next_run > now {
+                    continue;
+                }
+                if job.next_run(next_run) <= now {
+                    tracing::info!(
+                        "Catching up on missed runs of {} since {}.",
+                        job.name(),
+                        last_run
+                    );
+                }
+            }
+
+            job.run(now).await;
+
+            if let Err(e) = self.runs.save_job_run(job.name(), now) {
+                tracing::error!(
+                    "An error occurred saving the last run of {}: {}",
+                    job.name(),
+                    e
+                );
+            }
+        }
+    }
+
+    /// Spawns a task ticking the scheduler with the time of its clock until the bot shuts down.
+    pub fn spawn(self) {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(self.tick_interval);
+            loop {
+                interval.tick().await;
+                self.tick(self.clock.now()).await;
+            }
+        });
+    }
+}
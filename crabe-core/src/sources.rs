@@ -0,0 +1,146 @@
+use std::{borrow::Cow, fmt};
+
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+lazy_static! {
+    static ref CUSTOM_EMOJI: Regex = Regex::new(r"<a?:(\w+):(\d+)>").unwrap();
+}
+
+/// Replaces the markup of custom emoji such as `<:rust:123>` with spaces, so their names aren't
+/// mistaken for text. Custom emoji count through the emoji source instead.
+pub fn strip_custom_emoji(content: &str) -> Cow<'_, str> {
+    CUSTOM_EMOJI.replace_all(content, " ")
+}
+
+/// Where a mention of Rust was found.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum MentionSource {
+    Text,
+    Emoji,
+    Sticker,
+    Reaction,
+}
+
+impl MentionSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MentionSource::Text => "text",
+            MentionSource::Emoji => "emoji",
+            MentionSource::Sticker => "sticker",
+            MentionSource::Reaction => "reaction",
+        }
+    }
+}
+
+impl fmt::Display for MentionSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Whether a source of mentions is counted and how many mentions it counts as.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct SourceSettings {
+    pub enabled: bool,
+    pub weight: usize,
+}
+
+impl Default for SourceSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            weight: 1,
+        }
+    }
+}
+
+/// An emoji used in a message or as a reaction.
+pub enum Emoji<'a> {
+    Custom { id: u64, name: &'a str },
+    Unicode(&'a str),
+}
+
+/// The sources mentions of Rust are counted from, and which emoji and stickers count as one.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct Sources {
+    pub text: SourceSettings,
+    pub emoji: SourceSettings,
+    pub stickers: SourceSettings,
+    pub reactions: SourceSettings,
+    /// Parts of the names of custom emoji and stickers that count as a mention, e.g. `ferris`.
+    pub names: Vec<String>,
+    /// The IDs of custom emoji and stickers that count as a mention regardless of their name.
+    pub ids: Vec<u64>,
+    /// The Unicode emoji that count as a mention, e.g. 🦀.
+    pub unicode_emoji: Vec<String>,
+}
+
+impl Default for Sources {
+    fn default() -> Self {
+        Self {
+            text: SourceSettings::default(),
+            emoji: SourceSettings::default(),
+            stickers: SourceSettings::default(),
+            reactions: SourceSettings::default(),
+            names: vec!["rust".to_string(), "ferris".to_string()],
+            ids: Vec::new(),
+            unicode_emoji: vec!["🦀".to_string()],
+        }
+    }
+}
+
+impl Sources {
+    pub fn settings(&self, source: MentionSource) -> SourceSettings {
+        match source {
+            MentionSource::Text => self.text,
+            MentionSource::Emoji => self.emoji,
+            MentionSource::Sticker => self.stickers,
+            MentionSource::Reaction => self.reactions,
+        }
+    }
+
+    fn matches_name(&self, id: u64, name: &str) -> bool {
+        let name = name.to_lowercase();
+        self.ids.contains(&id)
+            || self
+                .names
+                .iter()
+                .any(|part| name.contains(&part.to_lowercase()))
+    }
+
+    pub fn matches_emoji(&self, emoji: &Emoji<'_>) -> bool {
+        match *emoji {
+            Emoji::Custom { id, name } => self.matches_name(id, name),
+            Emoji::Unicode(emoji) => self.unicode_emoji.iter().any(|e| e == emoji),
+        }
+    }
+
+    pub fn matches_sticker(&self, id: u64, name: &str) -> bool {
+        self.matches_name(id, name)
+    }
+
+    /// Finds the first emoji in a message that counts as a mention, returning how it was written.
+    pub fn detect_emoji(&self, content: &str) -> Option<String> {
+        CUSTOM_EMOJI
+            .captures_iter(content)
+            .find(|captures| {
+                let id = captures[2].parse().unwrap_or_default();
+                self.matches_emoji(&Emoji::Custom {
+                    id,
+                    name: &captures[1],
+                })
+            })
+            .map(|captures| captures[0].to_string())
+            .or_else(|| {
+                self.unicode_emoji
+                    .iter()
+                    .find(|emoji| content.contains(emoji.as_str()))
+                    .cloned()
+            })
+    }
+}
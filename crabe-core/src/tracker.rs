@@ -0,0 +1,140 @@
+use std::{collections::HashMap, hash::Hash, time::Duration};
+
+use chrono::{DateTime, Utc};
+
+use crate::record::Record;
+
+/// Mentions of Rust counted at once, e.g. all mentions of a single user found in a message.
+///
+/// Channels and users are identified by `C` and `U`, so the tracker works with the IDs of any
+/// chat platform.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Tally<C, U> {
+    pub channel_id: C,
+    pub user_id: U,
+    /// How many mentions these count as.
+    pub weight: usize,
+    pub at: DateTime<Utc>,
+}
+
+/// A correction an admin made to the counts or records.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CorrectionKind<C, U> {
+    /// Sets how many times a user has mentioned Rust.
+    MentionCount { user_id: U, count: usize },
+    /// Sets the record of a channel, or of the whole guild if no channel is given, keeping the
+    /// ongoing streak. A record without a duration is cleared, so any streak beats it.
+    Record {
+        channel_id: Option<C>,
+        duration: Option<Duration>,
+    },
+}
+
+/// The outcome of tracking a [`Tally`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Tracked {
+    /// How many times the user has mentioned Rust so far.
+    pub mention_count: usize,
+    /// The record of the guild before the mentions ended its streak.
+    pub previous: Option<Duration>,
+    /// The streak the mentions ended if it beat the record of the guild.
+    pub beaten: Option<Duration>,
+    /// The streak the mentions ended if it beat the record of their channel.
+    pub beaten_channel: Option<Duration>,
+}
+
+/// The counts and records of a single guild.
+///
+/// They are derived from the log of mentions and corrections, and can be rebuilt from it with
+/// [`GuildState::replay`].
+#[derive(Clone, Debug)]
+pub struct GuildState<C, U> {
+    pub record: Record,
+    pub channel_records: HashMap<C, Record>,
+    pub mention_counts: HashMap<U, usize>,
+    pub last_report: Option<DateTime<Utc>>,
+}
+
+impl<C, U> Default for GuildState<C, U> {
+    fn default() -> Self {
+        Self {
+            record: Record::default(),
+            channel_records: HashMap::new(),
+            mention_counts: HashMap::new(),
+            last_report: None,
+        }
+    }
+}
+
+impl<C: Eq + Hash, U: Eq + Hash> PartialEq for GuildState<C, U> {
+    fn eq(&self, other: &Self) -> bool {
+        self.record == other.record
+            && self.channel_records == other.channel_records
+            && self.mention_counts == other.mention_counts
+            && self.last_report == other.last_report
+    }
+}
+
+impl<C: Copy + Eq + Hash, U: Copy + Eq + Hash> GuildState<C, U> {
+    /// Counts the mentions and ends the ongoing streaks of the guild and the channel.
+    pub fn track(&mut self, tally: &Tally<C, U>) -> Tracked {
+        let mention_count = {
+            let count = self.mention_counts.entry(tally.user_id).or_default();
+            *count += tally.weight;
+            *count
+        };
+        let previous = self.record.duration;
+        let beaten = self.record.end_streak(tally.at);
+        let beaten_channel = self
+            .channel_records
+            .entry(tally.channel_id)
+            .or_default()
+            .end_streak(tally.at);
+
+        Tracked {
+            mention_count,
+            previous,
+            beaten,
+            beaten_channel,
+        }
+    }
+
+    pub fn correct(&mut self, kind: &CorrectionKind<C, U>) {
+        match *kind {
+            CorrectionKind::MentionCount { user_id, count } => {
+                self.mention_counts.insert(user_id, count);
+            }
+            CorrectionKind::Record {
+                channel_id,
+                duration,
+            } => {
+                let record = match channel_id {
+                    Some(channel_id) => self.channel_records.entry(channel_id).or_default(),
+                    None => &mut self.record,
+                };
+                record.duration = duration;
+            }
+        }
+    }
+
+    /// Applies tallies and corrections from the log on top of this state in the order of their
+    /// times, as if they were tracked one by one. Tallies go first when both happened at once.
+    ///
+    /// Both have to be sorted by their times already.
+    pub fn replay(
+        &mut self,
+        tallies: impl IntoIterator<Item = Tally<C, U>>,
+        corrections: impl IntoIterator<Item = (DateTime<Utc>, CorrectionKind<C, U>)>,
+    ) {
+        let mut corrections = corrections.into_iter().peekable();
+        for tally in tallies {
+            while let Some((_, kind)) = corrections.next_if(|(at, _)| *at < tally.at) {
+                self.correct(&kind);
+            }
+            self.track(&tally);
+        }
+        for (_, kind) in corrections {
+            self.correct(&kind);
+        }
+    }
+}
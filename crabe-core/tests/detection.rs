@@ -0,0 +1,134 @@
+use crabe_core::detection::{Detection, Detector, KeywordRule, KeywordRules, MatchMode};
+
+fn detector(include: &[(&str, MatchMode)], exclude: &[(&str, MatchMode)]) -> Detector {
+    let rules = |rules: &[(&str, MatchMode)]| {
+        rules
+            .iter()
+            .map(|&(pattern, mode)| KeywordRule::new(pattern, mode))
+            .collect()
+    };
+    Detector::new(&KeywordRules {
+        include: rules(include),
+        exclude: rules(exclude),
+    })
+    .unwrap()
+}
+
+fn detected(detector: &Detector, text: &str) -> Option<String> {
+    detector
+        .detect(text)
+        .map(|Detection { matched, .. }| matched)
+}
+
+#[test]
+fn the_default_rules_are_valid() {
+    assert_eq!(KeywordRules::default().validate(), Ok(()));
+}
+
+#[test]
+fn rules_without_includes_or_with_empty_or_invalid_patterns_are_rejected() {
+    let rules = |pattern: &str, mode| KeywordRules {
+        include: vec![KeywordRule::new(pattern, mode)],
+        exclude: Vec::new(),
+    };
+
+    assert!(KeywordRules {
+        include: Vec::new(),
+        exclude: Vec::new(),
+    }
+    .validate()
+    .is_err());
+    assert!(rules("  ", MatchMode::WholeWord).validate().is_err());
+    assert!(rules("rust(", MatchMode::WholeWord).validate().is_err());
+}
+
+#[test]
+fn patterns_matching_an_empty_text_are_rejected() {
+    for pattern in ["rust|", "(?:)", "a*", ".*", "$"] {
+        for mode in [MatchMode::WholeWord, MatchMode::Substring] {
+            let rules = KeywordRules {
+                include: vec![KeywordRule::new(pattern, mode)],
+                exclude: Vec::new(),
+            };
+            let error = rules.validate().unwrap_err();
+            assert!(error.contains("matches an empty text"), "{}", error);
+        }
+    }
+
+    let excluded = KeywordRules {
+        include: KeywordRules::default().include,
+        exclude: vec![KeywordRule::new("belt|", MatchMode::Substring)],
+    };
+    assert!(excluded.validate().is_err());
+}
+
+#[test]
+fn whole_words_only_match_between_word_boundaries() {
+    let detector = detector(&[("rust", MatchMode::WholeWord)], &[]);
+
+    assert_eq!(
+        detected(&detector, "I love Rust!"),
+        Some("Rust".to_string())
+    );
+    assert_eq!(detected(&detector, "RUST"), Some("RUST".to_string()));
+    assert_eq!(detected(&detector, "rusty nails"), None);
+    assert_eq!(detected(&detector, "a rustacean"), None);
+    assert_eq!(detected(&detector, "trust me"), None);
+}
+
+#[test]
+fn substrings_match_anywhere() {
+    let detector = detector(&[("rust", MatchMode::Substring)], &[]);
+
+    assert_eq!(detected(&detector, "a Rustacean"), Some("Rust".to_string()));
+    assert_eq!(detected(&detector, "trust me"), Some("rust".to_string()));
+    assert_eq!(detected(&detector, "python"), None);
+}
+
+#[test]
+fn the_first_matching_include_is_reported() {
+    let detector = detector(
+        &[
+            ("ferris", MatchMode::WholeWord),
+            ("rust", MatchMode::WholeWord),
+        ],
+        &[],
+    );
+
+    assert_eq!(
+        detector.detect("rust and ferris"),
+        Some(Detection {
+            rule: "ferris".to_string(),
+            matched: "ferris".to_string(),
+        })
+    );
+    assert_eq!(
+        detector.detect("only rust").map(|detection| detection.rule),
+        Some("rust".to_string())
+    );
+}
+
+#[test]
+fn excluded_parts_dont_count_but_the_rest_does() {
+    let detector = detector(
+        &[("rust", MatchMode::WholeWord)],
+        &[("rust belt", MatchMode::WholeWord)],
+    );
+
+    assert_eq!(detected(&detector, "the rust belt"), None);
+    assert_eq!(
+        detected(&detector, "rust belt or rust?"),
+        Some("rust".to_string())
+    );
+}
+
+#[test]
+fn an_exclusion_only_has_to_overlap_a_match() {
+    let detector = detector(
+        &[("rust", MatchMode::Substring)],
+        &[("tbel", MatchMode::Substring)],
+    );
+
+    assert_eq!(detected(&detector, "rustbelt"), None);
+    assert_eq!(detected(&detector, "rust, belt"), Some("rust".to_string()));
+}
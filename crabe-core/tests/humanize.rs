@@ -0,0 +1,69 @@
+use std::time::Duration;
+
+use crabe_core::humanize::{humanize, Humanizer, Unit};
+
+const MINUTE: u64 = 60;
+const HOUR: u64 = 60 * MINUTE;
+const DAY: u64 = 24 * HOUR;
+
+fn seconds(seconds: u64) -> Duration {
+    Duration::from_secs(seconds)
+}
+
+#[test]
+fn units_are_pluralized_by_their_own_amount() {
+    assert_eq!(humanize(seconds(DAY + HOUR)), "1 day and 1 hour");
+    assert_eq!(humanize(seconds(2 * DAY + HOUR)), "2 days and 1 hour");
+    assert_eq!(humanize(seconds(HOUR + MINUTE)), "1 hour and 1 minute");
+    assert_eq!(humanize(seconds(MINUTE + 1)), "1 minute and 1 second");
+    assert_eq!(humanize(seconds(1)), "1 second");
+}
+
+#[test]
+fn units_up_to_years_are_used() {
+    assert_eq!(humanize(seconds(9 * DAY)), "1 week and 2 days");
+    assert_eq!(humanize(seconds(45 * DAY)), "1 month and 2 weeks");
+    assert_eq!(humanize(seconds(400 * DAY)), "1 year and 1 month");
+}
+
+#[test]
+fn units_that_are_zero_are_left_out() {
+    assert_eq!(humanize(seconds(3 * DAY)), "3 days");
+    assert_eq!(humanize(seconds(0)), "0 seconds");
+}
+
+#[test]
+fn smaller_units_are_truncated() {
+    // The minutes are beyond the two most significant units, even though the hours are zero.
+    assert_eq!(humanize(seconds(DAY + 59 * MINUTE)), "1 day");
+    assert_eq!(humanize(seconds(2 * HOUR + 59)), "2 hours");
+}
+
+#[test]
+fn the_precision_is_configurable() {
+    let duration = seconds(8 * DAY + 3 * HOUR + 4 * MINUTE + 5);
+
+    assert_eq!(
+        Humanizer::default().with_precision(1).humanize(duration),
+        "1 week"
+    );
+    assert_eq!(
+        Humanizer::default().with_precision(4).humanize(duration),
+        "1 week, 1 day, 3 hours and 4 minutes"
+    );
+    assert_eq!(
+        Humanizer::default().with_precision(0).humanize(duration),
+        "1 week"
+    );
+}
+
+#[test]
+fn the_units_are_configurable() {
+    let humanizer = Humanizer::default()
+        .with_largest_unit(Unit::Day)
+        .with_smallest_unit(Unit::Hour);
+
+    assert_eq!(humanizer.humanize(seconds(400 * DAY)), "400 days");
+    assert_eq!(humanizer.humanize(seconds(HOUR + 5 * MINUTE)), "1 hour");
+    assert_eq!(humanizer.humanize(seconds(5 * MINUTE)), "0 hours");
+}
@@ -0,0 +1,76 @@
+use std::collections::HashMap;
+
+use chrono::{TimeZone, Utc};
+use crabe_core::leaderboard::{page_count, rank, Window, PAGE_SIZE};
+
+#[test]
+fn the_week_starts_on_monday() {
+    // A Thursday.
+    let now = Utc.with_ymd_and_hms(2023, 3, 9, 15, 30, 0).unwrap();
+    assert_eq!(
+        Window::Week.bounds(now),
+        Some((Utc.with_ymd_and_hms(2023, 3, 6, 0, 0, 0).unwrap(), now))
+    );
+
+    let monday = Utc.with_ymd_and_hms(2023, 3, 6, 8, 0, 0).unwrap();
+    assert_eq!(
+        Window::Week.bounds(monday),
+        Some((Utc.with_ymd_and_hms(2023, 3, 6, 0, 0, 0).unwrap(), monday))
+    );
+}
+
+#[test]
+fn the_month_starts_on_its_first_day() {
+    let now = Utc.with_ymd_and_hms(2023, 3, 9, 15, 30, 0).unwrap();
+    assert_eq!(
+        Window::Month.bounds(now),
+        Some((Utc.with_ymd_and_hms(2023, 3, 1, 0, 0, 0).unwrap(), now))
+    );
+    assert_eq!(Window::AllTime.bounds(now), None);
+}
+
+#[test]
+fn custom_windows_include_both_dates() {
+    let window = Window::custom("2023-01-02", " 2023-01-08 ").unwrap();
+    let now = Utc.with_ymd_and_hms(2023, 3, 9, 15, 30, 0).unwrap();
+    assert_eq!(
+        window.bounds(now),
+        Some((
+            Utc.with_ymd_and_hms(2023, 1, 2, 0, 0, 0).unwrap(),
+            Utc.with_ymd_and_hms(2023, 1, 9, 0, 0, 0).unwrap(),
+        ))
+    );
+    assert_eq!(window.title(), "2023-01-02 to 2023-01-08");
+
+    let single_day = Window::custom("2023-01-02", "2023-01-02").unwrap();
+    assert_eq!(single_day.title(), "2023-01-02 to 2023-01-02");
+}
+
+#[test]
+fn reversed_or_malformed_dates_are_rejected() {
+    assert_eq!(
+        Window::custom("2023-01-08", "2023-01-02"),
+        Err("The start date has to be before the end date.".to_string())
+    );
+    assert_eq!(
+        Window::custom("02.01.2023", "2023-01-08"),
+        Err("`02.01.2023` is not a valid date, please use YYYY-MM-DD.".to_string())
+    );
+    assert!(Window::custom("2023-02-30", "2023-03-01").is_err());
+}
+
+#[test]
+fn ties_are_ranked_by_user() {
+    let counts = HashMap::from([(3, 5), (1, 2), (2, 5), (4, 7)]);
+    assert_eq!(rank(counts), vec![(4, 7), (2, 5), (3, 5), (1, 2)]);
+    assert!(rank(HashMap::<u64, usize>::new()).is_empty());
+}
+
+#[test]
+fn there_is_always_at_least_one_page() {
+    assert_eq!(page_count(0), 1);
+    assert_eq!(page_count(1), 1);
+    assert_eq!(page_count(PAGE_SIZE), 1);
+    assert_eq!(page_count(PAGE_SIZE + 1), 2);
+    assert_eq!(page_count(3 * PAGE_SIZE), 3);
+}
@@ -0,0 +1,141 @@
+use crabe_core::markdown::{parse, Kinds, Regions, Segment};
+
+/// The parts of a message wrapped in the given kind of markdown.
+fn marked(content: &str, kind: Kinds) -> Vec<&str> {
+    parse(content)
+        .into_iter()
+        .filter(|segment| segment.kinds.intersects(kind))
+        .map(|segment| segment.text)
+        .collect()
+}
+
+fn only_plain() -> Regions {
+    Regions {
+        plain: true,
+        quotes: false,
+        code_blocks: false,
+        inline_code: false,
+        spoilers: false,
+        links: false,
+    }
+}
+
+#[test]
+fn text_without_markdown_is_a_single_plain_segment() {
+    assert_eq!(
+        parse("I like rust"),
+        vec![Segment {
+            text: "I like rust",
+            kinds: Kinds::default(),
+        }]
+    );
+}
+
+#[test]
+fn quotes_last_until_the_end_of_their_line() {
+    let content = "> rust is great\nyes it is\n> isn't it";
+    assert_eq!(
+        marked(content, Kinds::QUOTE),
+        vec!["> rust is great\n", "> isn't it"]
+    );
+    assert_eq!(only_plain().filter(content), " yes it is\n ");
+}
+
+#[test]
+fn block_quotes_last_until_the_end_of_the_message() {
+    let content = "hi\n>>> rust\nand more\n";
+    assert_eq!(marked(content, Kinds::QUOTE), vec![">>> rust\nand more\n"]);
+    assert!(marked("a >>> b", Kinds::QUOTE).is_empty());
+}
+
+#[test]
+fn fenced_code_blocks_are_marked() {
+    let content = "look:\n```rs\nfn main() {}\n```\ndone";
+    assert_eq!(
+        marked(content, Kinds::CODE_BLOCK),
+        vec!["```rs\nfn main() {}\n```"]
+    );
+    assert!(marked("```unclosed rust", Kinds::CODE_BLOCK).is_empty());
+}
+
+#[test]
+fn inline_code_is_marked_with_single_or_double_backticks() {
+    assert_eq!(
+        marked("use `rust` and ``a ` b``", Kinds::INLINE_CODE),
+        vec!["`rust`", "``a ` b``"]
+    );
+    assert!(marked("a `` b", Kinds::INLINE_CODE).is_empty());
+}
+
+#[test]
+fn markdown_inside_code_is_ignored() {
+    let content = "```\n> ||rust|| https://rust-lang.org\n```";
+    let kinds = parse(content)
+        .into_iter()
+        .map(|segment| segment.kinds)
+        .collect::<Vec<_>>();
+    assert_eq!(kinds, vec![Kinds::CODE_BLOCK]);
+    assert!(marked("`||rust||`", Kinds::SPOILER).is_empty());
+}
+
+#[test]
+fn spoilers_are_marked_in_pairs() {
+    assert_eq!(
+        marked("||rust|| and ||more|| ||unpaired", Kinds::SPOILER),
+        vec!["||rust||", "||more||"]
+    );
+}
+
+#[test]
+fn nested_markdown_has_every_kind() {
+    let segments = parse("> ||rust||");
+    let spoiler = segments
+        .iter()
+        .find(|segment| segment.text == "||rust||")
+        .unwrap();
+    assert!(spoiler.kinds.intersects(Kinds::QUOTE));
+    assert!(spoiler.kinds.intersects(Kinds::SPOILER));
+
+    let regions = Regions {
+        spoilers: true,
+        quotes: false,
+        ..Regions::default()
+    };
+    assert_eq!(regions.filter("> ||rust||"), "  ");
+}
+
+#[test]
+fn links_are_marked_with_a_scheme_or_www() {
+    assert_eq!(
+        marked("see https://rust-lang.org/learn now", Kinds::LINK),
+        vec!["https://rust-lang.org/learn"]
+    );
+    assert_eq!(
+        marked("see www.rust-lang.org now", Kinds::LINK),
+        vec!["www.rust-lang.org"]
+    );
+    assert!(marked("I like rust. Really", Kinds::LINK).is_empty());
+}
+
+#[test]
+fn bare_domains_are_not_links() {
+    assert!(marked("I love rust.Anyway", Kinds::LINK).is_empty());
+    assert!(marked("check main.rs", Kinds::LINK).is_empty());
+    assert!(marked("see rust-lang.org now", Kinds::LINK).is_empty());
+
+    // So the mentions of Rust in them are still counted.
+    assert_eq!(
+        Regions::default().filter("I love rust.Anyway"),
+        "I love rust.Anyway"
+    );
+}
+
+#[test]
+fn disabled_regions_are_replaced_with_a_space() {
+    let regions = Regions::default();
+    assert_eq!(
+        regions.filter("a`b`c ||d|| https://e.com/f"),
+        "a`b`c ||d||  "
+    );
+    assert_eq!(only_plain().filter("a`b`c"), "a c");
+}
@@ -0,0 +1,81 @@
+use crabe_core::normalize::Normalization;
+
+fn only(layer: impl FnOnce(&mut Normalization)) -> Normalization {
+    let mut normalization = Normalization {
+        nfkc: false,
+        strip_invisible: false,
+        confusables: false,
+        collapse_spacing: false,
+        leetspeak: false,
+    };
+    layer(&mut normalization);
+    normalization
+}
+
+fn all() -> Normalization {
+    Normalization {
+        collapse_spacing: true,
+        leetspeak: true,
+        ..Normalization::default()
+    }
+}
+
+#[test]
+fn nfkc_turns_fullwidth_letters_into_ascii() {
+    let normalization = only(|n| n.nfkc = true);
+    assert_eq!(normalization.normalize("Ｒｕｓｔ"), "Rust");
+    assert_eq!(normalization.normalize("ﬁne"), "fine");
+}
+
+#[test]
+fn invisible_characters_are_stripped() {
+    let normalization = only(|n| n.strip_invisible = true);
+    assert_eq!(normalization.normalize("r\u{200b}ust"), "rust");
+    assert_eq!(normalization.normalize("ru\u{feff}s\u{2060}t"), "rust");
+    assert_eq!(normalization.normalize("r ust"), "r ust");
+}
+
+#[test]
+fn confusables_become_latin_letters_without_diacritics() {
+    let normalization = only(|n| n.confusables = true);
+    assert_eq!(normalization.normalize("Rսst"), "Rust");
+    assert_eq!(normalization.normalize("гust"), "rust");
+    assert_eq!(normalization.normalize("rüst"), "rust");
+}
+
+#[test]
+fn runs_of_single_letters_are_collapsed() {
+    let normalization = only(|n| n.collapse_spacing = true);
+    assert_eq!(normalization.normalize("r u s t"), "rust");
+    assert_eq!(normalization.normalize("I love r.u.s.t!"), "I love rust!");
+    assert_eq!(normalization.normalize("r-u-s-t is nice"), "rust is nice");
+}
+
+#[test]
+fn short_runs_and_digits_are_not_collapsed() {
+    let normalization = only(|n| n.collapse_spacing = true);
+    assert_eq!(normalization.normalize("I am a b c"), "I am a b c");
+    assert_eq!(normalization.normalize("x 1 2 3 y"), "x 1 2 3 y");
+}
+
+#[test]
+fn leetspeak_is_replaced_in_words_only() {
+    let normalization = only(|n| n.leetspeak = true);
+    assert_eq!(normalization.normalize("ru5t"), "rust");
+    assert_eq!(normalization.normalize("ru$7"), "rust");
+    assert_eq!(normalization.normalize("version 1.70"), "version 1.70");
+}
+
+#[test]
+fn all_layers_undo_combined_evasions() {
+    let normalization = all();
+    assert_eq!(normalization.normalize("Ｒ\u{200b}ս5t"), "Rust");
+    assert_eq!(normalization.normalize("r u s t"), "rust");
+}
+
+#[test]
+fn all_layers_leave_ordinary_text_alone() {
+    let normalization = all();
+    assert_eq!(normalization.normalize("x 1 2 3 y"), "x 1 2 3 y");
+    assert_eq!(normalization.normalize("I am a b c"), "I am a b c");
+}
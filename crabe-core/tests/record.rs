@@ -0,0 +1,103 @@
+use std::time::Duration;
+
+use chrono::{Duration as ChronoDuration, TimeZone, Utc};
+use crabe_core::{
+    clock::{Clock, MockClock},
+    record::Record,
+};
+
+fn clock() -> MockClock {
+    MockClock::new(Utc.with_ymd_and_hms(2023, 1, 2, 9, 0, 0).unwrap())
+}
+
+#[test]
+fn the_first_mention_starts_without_a_record() {
+    let clock = clock();
+    let mut record = Record::default();
+
+    assert_eq!(record.end_streak(clock.now()), None);
+    assert_eq!(record.last_mention, Some(clock.now()));
+    assert_eq!(record.duration, Some(Duration::ZERO));
+}
+
+#[test]
+fn a_longer_streak_beats_the_record() {
+    let clock = clock();
+    let mut record = Record::default();
+    record.end_streak(clock.now());
+
+    let now = clock.advance(ChronoDuration::days(5));
+    assert_eq!(
+        record.end_streak(now),
+        Some(Duration::from_secs(5 * 24 * 60 * 60))
+    );
+
+    let now = clock.advance(ChronoDuration::days(2));
+    assert_eq!(record.end_streak(now), None);
+    assert_eq!(record.last_mention, Some(now));
+    assert_eq!(record.duration, Some(Duration::from_secs(5 * 24 * 60 * 60)));
+}
+
+#[test]
+fn late_mentions_never_move_the_last_mention_back() {
+    let clock = clock();
+    let mut record = Record::default();
+    let earlier = clock.now();
+    let later = clock.advance(ChronoDuration::hours(1));
+
+    record.end_streak(later);
+    assert_eq!(record.end_streak(earlier), None);
+    assert_eq!(record.last_mention, Some(later));
+}
+
+#[test]
+fn the_ongoing_streak_is_reported_once_it_beats_the_record() {
+    let clock = clock();
+    let mut record = Record::default();
+    record.end_streak(clock.now());
+    record.end_streak(clock.advance(ChronoDuration::days(1)));
+
+    assert_eq!(
+        record.ongoing_streak_beating(clock.advance(ChronoDuration::hours(12))),
+        None
+    );
+    assert_eq!(
+        record.ongoing_streak_beating(clock.advance(ChronoDuration::days(1))),
+        Some((
+            Duration::from_secs(36 * 60 * 60),
+            Some(Duration::from_secs(24 * 60 * 60))
+        ))
+    );
+}
+
+#[test]
+fn a_record_of_zero_is_not_worth_announcing() {
+    let clock = clock();
+    let mut record = Record::default();
+    record.end_streak(clock.now());
+
+    assert_eq!(
+        record.ongoing_streak_beating(clock.advance(ChronoDuration::days(3))),
+        None
+    );
+}
+
+#[test]
+fn any_streak_beats_a_cleared_record() {
+    let clock = clock();
+    let mut record = Record::default();
+    record.end_streak(clock.now());
+    record.end_streak(clock.advance(ChronoDuration::days(1)));
+    record.duration = None;
+
+    assert_eq!(record.ongoing_streak_beating(clock.now()), None);
+    assert_eq!(
+        record.ongoing_streak_beating(clock.advance(ChronoDuration::hours(2))),
+        Some((Duration::from_secs(2 * 60 * 60), None))
+    );
+    assert_eq!(
+        record.end_streak(clock.now()),
+        Some(Duration::from_secs(2 * 60 * 60))
+    );
+    assert_eq!(record.duration, Some(Duration::from_secs(2 * 60 * 60)));
+}
@@ -0,0 +1,102 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use crabe_core::{
+    clock::{Clock, MockClock},
+    schedule::ReportSchedule,
+    scheduler::{Job, JobRuns, JobRunsError, Scheduler},
+};
+
+#[derive(Default)]
+struct MemoryJobRuns {
+    runs: Mutex<HashMap<String, DateTime<Utc>>>,
+}
+
+impl JobRuns for MemoryJobRuns {
+    fn load_job_run(&self, name: &str) -> Result<Option<DateTime<Utc>>, JobRunsError> {
+        Ok(self.runs.lock().unwrap().get(name).copied())
+    }
+
+    fn save_job_run(&self, name: &str, last_run: DateTime<Utc>) -> Result<(), JobRunsError> {
+        self.runs.lock().unwrap().insert(name.to_string(), last_run);
+        Ok(())
+    }
+}
+
+/// A daily job remembering when it ran.
+struct DailyJob {
+    runs: Arc<Mutex<Vec<DateTime<Utc>>>>,
+}
+
+#[async_trait::async_trait]
+impl Job for DailyJob {
+    fn name(&self) -> &'static str {
+        "daily"
+    }
+
+    fn next_run(&self, last_run: DateTime<Utc>) -> DateTime<Utc> {
+        last_run + Duration::days(1)
+    }
+
+    async fn run(&self, now: DateTime<Utc>) {
+        self.runs.lock().unwrap().push(now);
+    }
+}
+
+fn setup() -> (MockClock, Scheduler, Arc<Mutex<Vec<DateTime<Utc>>>>) {
+    let clock = MockClock::new(Utc.with_ymd_and_hms(2023, 1, 2, 9, 0, 0).unwrap());
+    let runs = Arc::new(Mutex::new(Vec::new()));
+    let scheduler = Scheduler::new(
+        Arc::new(MemoryJobRuns::default()),
+        Arc::new(MockClock::new(clock.now())),
+    )
+    .with_job(DailyJob { runs: runs.clone() });
+    (clock, scheduler, runs)
+}
+
+#[tokio::test]
+async fn jobs_run_once_they_are_due() {
+    let (clock, scheduler, runs) = setup();
+
+    scheduler.tick(clock.now()).await;
+    scheduler.tick(clock.advance(Duration::hours(23))).await;
+    assert_eq!(runs.lock().unwrap().len(), 1);
+
+    scheduler.tick(clock.advance(Duration::hours(1))).await;
+    assert_eq!(runs.lock().unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn missed_runs_are_caught_up_on_with_a_single_run() {
+    let (clock, scheduler, runs) = setup();
+
+    scheduler.tick(clock.now()).await;
+    let now = clock.advance(Duration::days(5));
+    scheduler.tick(now).await;
+
+    assert_eq!(*runs.lock().unwrap().last().unwrap(), now);
+    assert_eq!(runs.lock().unwrap().len(), 2);
+}
+
+#[test]
+fn a_weekly_report_is_due_after_jumping_ahead_by_days() {
+    let clock = MockClock::new(Utc.with_ymd_and_hms(2023, 1, 2, 9, 0, 0).unwrap());
+    let schedule = ReportSchedule::new("0 9 * * Mon", "UTC").unwrap();
+    let last_report = clock.now();
+
+    assert!(!schedule.is_due(last_report, clock.advance(Duration::days(5))));
+    assert!(schedule.is_due(last_report, clock.advance(Duration::days(2))));
+}
+
+#[test]
+fn report_schedules_respect_their_time_zone() {
+    let schedule = ReportSchedule::new("0 9 * * *", "Europe/Berlin").unwrap();
+    let next = schedule
+        .next_after(Utc.with_ymd_and_hms(2023, 1, 2, 0, 0, 0).unwrap())
+        .unwrap();
+
+    assert_eq!(next, Utc.with_ymd_and_hms(2023, 1, 2, 8, 0, 0).unwrap());
+}
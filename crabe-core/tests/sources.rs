@@ -0,0 +1,27 @@
+use crabe_core::sources::{strip_custom_emoji, Sources};
+
+#[test]
+fn custom_emoji_markup_is_stripped_from_the_text() {
+    assert_eq!(strip_custom_emoji("I <3 <:rust:123>!"), "I <3  !");
+    assert_eq!(strip_custom_emoji("<a:ferris_rust:456>rust"), " rust");
+    assert_eq!(strip_custom_emoji("no emoji here"), "no emoji here");
+}
+
+#[test]
+fn emoji_are_detected_by_name_id_or_unicode() {
+    let sources = Sources {
+        ids: vec![789],
+        ..Sources::default()
+    };
+
+    assert_eq!(
+        sources.detect_emoji("look <:Ferris:1>"),
+        Some("<:Ferris:1>".to_string())
+    );
+    assert_eq!(
+        sources.detect_emoji("<:crab:789>"),
+        Some("<:crab:789>".to_string())
+    );
+    assert_eq!(sources.detect_emoji("🦀 time"), Some("🦀".to_string()));
+    assert_eq!(sources.detect_emoji("<:python:2> rust"), None);
+}
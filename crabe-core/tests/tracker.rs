@@ -0,0 +1,152 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Duration as ChronoDuration, TimeZone, Utc};
+use crabe_core::tracker::{CorrectionKind, GuildState, Tally, Tracked};
+
+const CHANNEL: u64 = 1;
+const OTHER_CHANNEL: u64 = 2;
+const FERRIS: u64 = 10;
+const CORRO: u64 = 11;
+
+const DAY: u64 = 24 * 60 * 60;
+
+fn start() -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2023, 1, 2, 9, 0, 0).unwrap()
+}
+
+fn tally(channel_id: u64, user_id: u64, days: i64) -> Tally<u64, u64> {
+    Tally {
+        channel_id,
+        user_id,
+        weight: 1,
+        at: start() + ChronoDuration::days(days),
+    }
+}
+
+#[test]
+fn tracking_counts_mentions_and_ends_streaks() {
+    let mut state = GuildState::default();
+
+    assert_eq!(
+        state.track(&tally(CHANNEL, FERRIS, 0)),
+        Tracked {
+            mention_count: 1,
+            previous: None,
+            beaten: None,
+            beaten_channel: None,
+        }
+    );
+    assert_eq!(
+        state.track(&tally(OTHER_CHANNEL, FERRIS, 2)),
+        Tracked {
+            mention_count: 2,
+            previous: Some(Duration::ZERO),
+            beaten: Some(Duration::from_secs(2 * DAY)),
+            beaten_channel: None,
+        }
+    );
+    assert_eq!(
+        state.track(&tally(CHANNEL, CORRO, 3)),
+        Tracked {
+            mention_count: 1,
+            previous: Some(Duration::from_secs(2 * DAY)),
+            beaten: None,
+            beaten_channel: Some(Duration::from_secs(3 * DAY)),
+        }
+    );
+
+    assert_eq!(state.record.duration, Some(Duration::from_secs(2 * DAY)));
+    assert_eq!(
+        state.channel_records[&CHANNEL].duration,
+        Some(Duration::from_secs(3 * DAY))
+    );
+    assert_eq!(state.mention_counts[&FERRIS], 2);
+    assert_eq!(state.mention_counts[&CORRO], 1);
+}
+
+#[test]
+fn the_weight_of_a_tally_is_counted() {
+    let mut state = GuildState::default();
+    let tracked = state.track(&Tally {
+        weight: 3,
+        ..tally(CHANNEL, FERRIS, 0)
+    });
+    assert_eq!(tracked.mention_count, 3);
+}
+
+#[test]
+fn corrections_set_counts_and_records_keeping_the_streak() {
+    let mut state = GuildState::default();
+    state.track(&tally(CHANNEL, FERRIS, 0));
+
+    state.correct(&CorrectionKind::MentionCount {
+        user_id: FERRIS,
+        count: 42,
+    });
+    state.correct(&CorrectionKind::Record {
+        channel_id: None,
+        duration: Some(Duration::from_secs(5 * DAY)),
+    });
+    state.correct(&CorrectionKind::Record {
+        channel_id: Some(OTHER_CHANNEL),
+        duration: Some(Duration::from_secs(DAY)),
+    });
+
+    assert_eq!(state.mention_counts[&FERRIS], 42);
+    assert_eq!(state.record.duration, Some(Duration::from_secs(5 * DAY)));
+    assert_eq!(state.record.last_mention, Some(start()));
+    assert_eq!(
+        state.channel_records[&OTHER_CHANNEL].duration,
+        Some(Duration::from_secs(DAY))
+    );
+
+    // The corrected record has to be beaten by the next streak.
+    let tracked = state.track(&tally(CHANNEL, FERRIS, 4));
+    assert_eq!(tracked.beaten, None);
+    assert_eq!(tracked.mention_count, 43);
+}
+
+#[test]
+fn replaying_applies_tallies_and_corrections_in_order() {
+    let tallies = vec![
+        tally(CHANNEL, FERRIS, 0),
+        tally(CHANNEL, FERRIS, 1),
+        tally(CHANNEL, CORRO, 4),
+    ];
+    let corrections = vec![
+        (
+            start() + ChronoDuration::days(1),
+            CorrectionKind::MentionCount {
+                user_id: FERRIS,
+                count: 10,
+            },
+        ),
+        (
+            start() + ChronoDuration::days(2),
+            CorrectionKind::Record {
+                channel_id: None,
+                duration: Some(Duration::from_secs(5 * DAY)),
+            },
+        ),
+    ];
+
+    let mut replayed = GuildState::default();
+    replayed.replay(tallies.clone(), corrections);
+
+    // The mention on the day of the count correction goes first, so it is overwritten.
+    assert_eq!(replayed.mention_counts[&FERRIS], 10);
+    assert_eq!(replayed.mention_counts[&CORRO], 1);
+    assert_eq!(replayed.record.duration, Some(Duration::from_secs(5 * DAY)));
+    assert_eq!(
+        replayed.channel_records[&CHANNEL].duration,
+        Some(Duration::from_secs(3 * DAY))
+    );
+
+    let mut tracked = GuildState::default();
+    for tally in &tallies {
+        tracked.track(tally);
+    }
+    let mut untouched = GuildState::default();
+    untouched.replay(tallies, Vec::new());
+    assert_eq!(untouched, tracked);
+}
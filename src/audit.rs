@@ -0,0 +1,155 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use crabe_core::humanize::humanize;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use serenity::{
+    client::Context,
+    model::prelude::{GuildId, Mentionable, UserId},
+};
+
+use crate::{settings::GuildSettings, storage::Storage};
+
+/// The longest a value is shown in the audit log before it is cut off.
+const MAX_VALUE_LENGTH: usize = 100;
+
+/// What an admin did.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditAction {
+    /// Changed a setting, given as the target.
+    ConfigChange,
+    /// Changed the mention count of the user given as the target.
+    CountChange,
+    /// Changed the record, in seconds, of the channel given as the target or of the whole guild.
+    RecordChange,
+    /// Disqualified the message given as the target, taking back the mentions in the old value.
+    Disqualification,
+    /// Rebuilt the counts and records from the mention log, first disqualifying as many mentions
+    /// as the new value that matched the keyword rule given as the target, if any.
+    Rebuild,
+}
+
+/// An entry of the audit log, recording who did what to which value.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AuditEntry {
+    pub guild_id: GuildId,
+    pub actor_id: UserId,
+    pub action: AuditAction,
+    /// What the action was applied to, such as the key of a setting, or empty for the guild.
+    pub target: String,
+    pub old_value: Value,
+    pub new_value: Value,
+    pub at: DateTime<Utc>,
+}
+
+impl AuditEntry {
+    /// Describes the entry as a line of markdown, with the time and the actor rendered by Discord.
+    pub fn describe(&self) -> String {
+        let action = match self.action {
+            AuditAction::ConfigChange => format!(
+                "changed `{}` from `{}` to `{}`",
+                self.target,
+                shorten(&self.old_value),
+                shorten(&self.new_value)
+            ),
+            AuditAction::CountChange => format!(
+                "set the mention count of <@{}> from {} to {}",
+                self.target, self.old_value, self.new_value
+            ),
+            AuditAction::RecordChange => {
+                let place = match self.target.as_str() {
+                    "" => "this server".to_string(),
+                    channel_id => format!("<#{}>", channel_id),
+                };
+                format!(
+                    "set the record of {} from {} to {}",
+                    place,
+                    duration(&self.old_value),
+                    duration(&self.new_value)
+                )
+            }
+            AuditAction::Disqualification => {
+                let mentions = self.old_value["mentions"]
+                    .as_object()
+                    .map(|weights| weights.values().filter_map(Value::as_u64).sum::<u64>())
+                    .unwrap_or_default();
+                format!(
+                    "disqualified https://discord.com/channels/{}/{}/{}, taking back {} {}",
+                    self.guild_id,
+                    self.old_value["channel_id"].as_str().unwrap_or_default(),
+                    self.target,
+                    mentions,
+                    if mentions == 1 { "mention" } else { "mentions" }
+                )
+            }
+            AuditAction::Rebuild => match self.target.as_str() {
+                "" => "rebuilt the counts and records from the mention log".to_string(),
+                rule => {
+                    let mentions = self.new_value.as_u64().unwrap_or_default();
+                    format!(
+                        "disqualified {} {} matching `{}` and rebuilt the counts and records",
+                        mentions,
+                        if mentions == 1 { "mention" } else { "mentions" },
+                        rule
+                    )
+                }
+            },
+        };
+        format!(
+            "<t:{}:f> {} {}.",
+            self.at.timestamp(),
+            self.actor_id.mention(),
+            action
+        )
+    }
+}
+
+/// Shows a duration given in seconds.
+fn duration(seconds: &Value) -> String {
+    match seconds.as_u64() {
+        Some(seconds) => humanize(Duration::from_secs(seconds)),
+        None => "none".to_string(),
+    }
+}
+
+fn shorten(value: &Value) -> String {
+    let value = value.to_string();
+    match value.char_indices().nth(MAX_VALUE_LENGTH) {
+        Some((end, _)) => format!("{}…", &value[..end]),
+        None => value,
+    }
+}
+
+/// Saves an entry of the audit log.
+pub fn save(storage: &dyn Storage, entry: &AuditEntry) {
+    if let Err(e) = storage.save_audit_entry(entry) {
+        tracing::error!(
+            "An error occurred saving an audit log entry of {}: {}",
+            entry.guild_id,
+            e
+        );
+    }
+}
+
+/// Mirrors an entry of the audit log to the mod log channel of the guild, if it has one.
+pub async fn mirror(context: &Context, settings: &GuildSettings, entry: &AuditEntry) {
+    let channel_id = match settings.mod_log_channel {
+        Some(channel_id) => channel_id,
+        None => return,
+    };
+    if let Err(e) = channel_id
+        .send_message(&context.http, |m| {
+            m.embed(|e| {
+                e.title("🦀 Audit Log 🦀")
+                    .description(entry.describe())
+                    .color(settings.embed.color)
+                    .footer(|f| f.text(&settings.embed.footer))
+            })
+        })
+        .await
+    {
+        tracing::error!("An error occurred mirroring an audit log entry: {}", e);
+    }
+}
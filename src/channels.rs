@@ -0,0 +1,70 @@
+use serde::{Deserialize, Serialize};
+use serenity::{
+    client::Context,
+    model::{channel::Channel, prelude::ChannelId},
+};
+
+/// The channels mentions of Rust are counted in.
+///
+/// Both lists may contain channels, categories and threads. A channel is decided on by the most
+/// specific entry listing it, its parent channel in case of a thread, or its category, so threads
+/// inherit from their parent channel unless they are listed themselves. Channels not listed at all
+/// are counted unless something is included explicitly.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct Channels {
+    pub include: Vec<ChannelId>,
+    pub exclude: Vec<ChannelId>,
+}
+
+impl Channels {
+    /// Whether mentions are counted in a channel, only looking up its ancestors when needed.
+    pub async fn allows_channel(&self, context: &Context, channel_id: ChannelId) -> bool {
+        if self.include.is_empty() && self.exclude.is_empty() {
+            return true;
+        }
+        self.allows(&ancestry(context, channel_id).await)
+    }
+
+    /// Whether mentions are counted in a channel, given the channel followed by its parent
+    /// channel if it is a thread, and then its category.
+    pub fn allows(&self, ancestry: &[ChannelId]) -> bool {
+        ancestry
+            .iter()
+            .find_map(|channel_id| {
+                if self.exclude.contains(channel_id) {
+                    Some(false)
+                } else if self.include.contains(channel_id) {
+                    Some(true)
+                } else {
+                    None
+                }
+            })
+            .unwrap_or(self.include.is_empty())
+    }
+}
+
+/// Returns a channel followed by its parent channel if it is a thread, and then its category.
+async fn ancestry(context: &Context, channel_id: ChannelId) -> Vec<ChannelId> {
+    let mut ancestry = vec![channel_id];
+
+    // A thread has its channel as parent, which in turn may have a category as parent.
+    while ancestry.len() < 3 {
+        let current = ancestry[ancestry.len() - 1];
+        let parent_id = match current.to_channel(context).await {
+            Ok(Channel::Guild(channel)) => channel.parent_id,
+            Ok(_) => None,
+            Err(e) => {
+                tracing::error!("An error occurred fetching the channel {}: {}", current, e);
+                None
+            }
+        };
+
+        match parent_id {
+            Some(parent_id) => ancestry.push(parent_id),
+            None => break,
+        }
+    }
+
+    ancestry
+}
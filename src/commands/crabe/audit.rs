@@ -0,0 +1,71 @@
+use serenity::{
+    builder::CreateApplicationCommandOption,
+    model::{
+        application::{
+            command::CommandOptionType,
+            interaction::application_command::{CommandDataOption, CommandDataOptionValue},
+        },
+        prelude::GuildId,
+    },
+};
+
+use crate::{commands::find_option, storage::Storage};
+
+/// The number of entries listed unless asked for another number.
+const DEFAULT_COUNT: usize = 10;
+/// The most entries listed at once.
+const MAX_COUNT: usize = 25;
+/// The longest message Discord accepts, which cuts off the oldest entries listed.
+const MAX_LENGTH: usize = 2000;
+
+pub fn register(
+    subcommand: &mut CreateApplicationCommandOption,
+) -> &mut CreateApplicationCommandOption {
+    subcommand
+        .name("audit")
+        .description("Lists the recent changes to the settings and corrections by admins")
+        .kind(CommandOptionType::SubCommand)
+        .create_sub_option(|option| {
+            option
+                .name("count")
+                .description("The number of entries to list")
+                .kind(CommandOptionType::Integer)
+                .min_int_value(1)
+                .max_int_value(MAX_COUNT)
+        })
+}
+
+/// Lists the most recent entries of the audit log, the newest first.
+pub fn run(storage: &dyn Storage, guild_id: GuildId, subcommand: &CommandDataOption) -> String {
+    let count = match find_option(&subcommand.options, "count") {
+        Some(CommandDataOptionValue::Integer(count)) => {
+            (*count).clamp(1, MAX_COUNT as i64) as usize
+        }
+        _ => DEFAULT_COUNT,
+    };
+
+    match storage.load_audit_entries(guild_id, count) {
+        Ok(entries) if entries.is_empty() => "Nothing has been changed yet.".to_string(),
+        Ok(entries) => {
+            let mut lines = Vec::new();
+            let mut length = 0;
+            for entry in entries {
+                let line = entry.describe();
+                length += line.len() + 1;
+                if length > MAX_LENGTH {
+                    break;
+                }
+                lines.push(line);
+            }
+            lines.join("\n")
+        }
+        Err(e) => {
+            tracing::error!(
+                "An error occurred loading the audit log of {}: {}",
+                guild_id,
+                e
+            );
+            "The audit log could not be loaded, please try again later.".to_string()
+        }
+    }
+}
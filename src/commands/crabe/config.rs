@@ -0,0 +1,465 @@
+use crabe_core::detection::{KeywordRule, MatchMode};
+use serde_json::{json, Value};
+use serenity::{
+    builder::CreateApplicationCommandOption,
+    model::{
+        application::{
+            command::CommandOptionType,
+            interaction::application_command::{CommandDataOption, CommandDataOptionValue},
+        },
+        channel::ChannelType,
+        prelude::{GuildId, Mentionable},
+    },
+};
+
+use crate::{
+    commands::find_option,
+    settings::{AnnounceLevel, GuildSettings, SettingChange, Settings, SettingsError},
+};
+
+pub fn register(group: &mut CreateApplicationCommandOption) -> &mut CreateApplicationCommandOption {
+    group
+        .name("config")
+        .description("Shows or changes the settings of this server")
+        .kind(CommandOptionType::SubCommandGroup)
+        .create_sub_option(|subcommand| {
+            subcommand
+                .name("show")
+                .description("Shows the current settings")
+                .kind(CommandOptionType::SubCommand)
+        })
+        .create_sub_option(|subcommand| {
+            subcommand
+                .name("report-channel")
+                .description("Changes the channel the reports are posted to")
+                .kind(CommandOptionType::SubCommand)
+                .create_sub_option(|option| {
+                    option
+                        .name("channel")
+                        .description("The channel, or none to use the channel named by default")
+                        .kind(CommandOptionType::Channel)
+                        .channel_types(&[ChannelType::Text, ChannelType::News])
+                })
+        })
+        .create_sub_option(|subcommand| {
+            subcommand
+                .name("schedule")
+                .description("Changes when the reports are posted")
+                .kind(CommandOptionType::SubCommand)
+                .create_sub_option(|option| {
+                    option
+                        .name("cron")
+                        .description("A cron expression such as `0 9 * * Mon`")
+                        .kind(CommandOptionType::String)
+                        .required(true)
+                })
+                .create_sub_option(|option| {
+                    option
+                        .name("timezone")
+                        .description("The IANA time zone of the schedule, e.g. Europe/Berlin")
+                        .kind(CommandOptionType::String)
+                })
+        })
+        .create_sub_option(|subcommand| {
+            subcommand
+                .name("keyword-add")
+                .description("Adds a keyword counted as, or excluded from, a mention of Rust")
+                .kind(CommandOptionType::SubCommand)
+                .create_sub_option(|option| {
+                    option
+                        .name("pattern")
+                        .description("A case-insensitive regular expression")
+                        .kind(CommandOptionType::String)
+                        .required(true)
+                })
+                .create_sub_option(|option| {
+                    option
+                        .name("mode")
+                        .description("Where the pattern may appear, as a whole word by default")
+                        .kind(CommandOptionType::String)
+                        .add_string_choice("Whole word", "whole_word")
+                        .add_string_choice("Substring", "substring")
+                })
+                .create_sub_option(|option| {
+                    option
+                        .name("exclude")
+                        .description("Whether matches of the pattern never count as a mention")
+                        .kind(CommandOptionType::Boolean)
+                })
+        })
+        .create_sub_option(|subcommand| {
+            subcommand
+                .name("keyword-remove")
+                .description("Removes a keyword")
+                .kind(CommandOptionType::SubCommand)
+                .create_sub_option(|option| {
+                    option
+                        .name("pattern")
+                        .description("The pattern of the keyword")
+                        .kind(CommandOptionType::String)
+                        .required(true)
+                })
+                .create_sub_option(|option| {
+                    option
+                        .name("exclude")
+                        .description("Whether the keyword is an excluded one")
+                        .kind(CommandOptionType::Boolean)
+                })
+        })
+        .create_sub_option(|subcommand| {
+            subcommand
+                .name("exclude-channel")
+                .description("Stops counting the mentions in a channel, category or thread")
+                .kind(CommandOptionType::SubCommand)
+                .create_sub_option(|option| {
+                    option
+                        .name("channel")
+                        .description("The channel, category or thread")
+                        .kind(CommandOptionType::Channel)
+                        .required(true)
+                })
+        })
+        .create_sub_option(|subcommand| {
+            subcommand
+                .name("unexclude-channel")
+                .description("Counts the mentions in an excluded channel, category or thread again")
+                .kind(CommandOptionType::SubCommand)
+                .create_sub_option(|option| {
+                    option
+                        .name("channel")
+                        .description("The channel, category or thread")
+                        .kind(CommandOptionType::Channel)
+                        .required(true)
+                })
+        })
+        .create_sub_option(|subcommand| {
+            subcommand
+                .name("announcements")
+                .description("Changes which streaks and records are announced")
+                .kind(CommandOptionType::SubCommand)
+                .create_sub_option(|option| {
+                    option
+                        .name("milestones")
+                        .description("Whether to announce when the ongoing streak beats the record")
+                        .kind(CommandOptionType::Boolean)
+                })
+                .create_sub_option(|option| {
+                    option
+                        .name("records")
+                        .description("Which records are announced when a mention ends a streak")
+                        .kind(CommandOptionType::String)
+                        .add_string_choice("Server", "server")
+                        .add_string_choice("Channel", "channel")
+                        .add_string_choice("Both", "both")
+                        .add_string_choice("Off", "off")
+                })
+        })
+        .create_sub_option(|subcommand| {
+            subcommand
+                .name("admin-role")
+                .description("Changes the role allowed to manage the bot besides server managers")
+                .kind(CommandOptionType::SubCommand)
+                .create_sub_option(|option| {
+                    option
+                        .name("role")
+                        .description("The role, or none to only allow server managers")
+                        .kind(CommandOptionType::Role)
+                })
+        })
+        .create_sub_option(|subcommand| {
+            subcommand
+                .name("mod-log")
+                .description("Changes the channel the audit log is mirrored to")
+                .kind(CommandOptionType::SubCommand)
+                .create_sub_option(|option| {
+                    option
+                        .name("channel")
+                        .description("The channel, or none to stop mirroring the audit log")
+                        .kind(CommandOptionType::Channel)
+                        .channel_types(&[ChannelType::Text])
+                })
+        })
+        .create_sub_option(|subcommand| {
+            subcommand
+                .name("set")
+                .description("Changes any single setting")
+                .kind(CommandOptionType::SubCommand)
+                .create_sub_option(|option| {
+                    option
+                        .name("key")
+                        .description("The name of the setting, e.g. report_channel")
+                        .kind(CommandOptionType::String)
+                        .required(true)
+                })
+                .create_sub_option(|option| {
+                    option
+                        .name("value")
+                        .description("The new value of the setting")
+                        .kind(CommandOptionType::String)
+                        .required(true)
+                })
+        })
+}
+
+/// Runs a subcommand of `/crabe config`, returning the response to show to the admin along with
+/// the settings it changed.
+pub fn run(
+    settings: &Settings,
+    guild_id: GuildId,
+    current: &GuildSettings,
+    subcommand: &CommandDataOption,
+) -> (String, Vec<SettingChange>) {
+    let result = match subcommand.name.as_str() {
+        "show" => return (show(current), Vec::new()),
+        "report-channel" => match find_option(&subcommand.options, "channel") {
+            Some(CommandDataOptionValue::Channel(channel)) => settings
+                .set_value(guild_id, "report_channel", json!(channel.id))
+                .map(|changes| {
+                    let response =
+                        format!("The reports are now posted to {}.", channel.id.mention());
+                    (response, changes)
+                }),
+            _ => settings
+                .set_value(guild_id, "report_channel", Value::Null)
+                .map(|changes| {
+                    let response = format!(
+                        "The reports are now posted to the channel named `{}`.",
+                        current.report_channel_name
+                    );
+                    (response, changes)
+                }),
+        },
+        "schedule" => {
+            let mut values = vec![("report_schedule", json!(string(subcommand, "cron")))];
+            if let Some(timezone) = string(subcommand, "timezone") {
+                values.push(("timezone", json!(timezone)));
+            }
+            settings
+                .set_values(guild_id, &values)
+                .map(|changes| ("Updated the report schedule.".to_string(), changes))
+        }
+        "keyword-add" => {
+            let pattern = string(subcommand, "pattern").unwrap_or_default();
+            let mode = match string(subcommand, "mode") {
+                Some("substring") => MatchMode::Substring,
+                _ => MatchMode::WholeWord,
+            };
+            let (key, mut keywords) = keywords(current, subcommand);
+            if keywords.iter().any(|keyword| keyword.pattern == pattern) {
+                let response = format!("`{}` is already in `{}`.", pattern, key);
+                return (response, Vec::new());
+            }
+
+            keywords.push(KeywordRule::new(pattern, mode));
+            settings
+                .set_value(guild_id, key, json!(keywords))
+                .map(|changes| (format!("Added `{}` to `{}`.", pattern, key), changes))
+        }
+        "keyword-remove" => {
+            let pattern = string(subcommand, "pattern").unwrap_or_default();
+            let (key, mut keywords) = keywords(current, subcommand);
+            if !keywords.iter().any(|keyword| keyword.pattern == pattern) {
+                return (format!("`{}` is not in `{}`.", pattern, key), Vec::new());
+            }
+
+            keywords.retain(|keyword| keyword.pattern != pattern);
+            settings
+                .set_value(guild_id, key, json!(keywords))
+                .map(|changes| (format!("Removed `{}` from `{}`.", pattern, key), changes))
+        }
+        "exclude-channel" | "unexclude-channel" => {
+            let channel_id = match find_option(&subcommand.options, "channel") {
+                Some(CommandDataOptionValue::Channel(channel)) => channel.id,
+                _ => return ("Please choose a channel.".to_string(), Vec::new()),
+            };
+            let exclude = subcommand.name == "exclude-channel";
+
+            let mut excluded = current.channels.exclude.clone();
+            match (excluded.contains(&channel_id), exclude) {
+                (true, true) => {
+                    let response = format!("{} is already excluded.", channel_id.mention());
+                    return (response, Vec::new());
+                }
+                (false, false) => {
+                    let response = format!("{} is not excluded.", channel_id.mention());
+                    return (response, Vec::new());
+                }
+                (false, true) => excluded.push(channel_id),
+                (true, false) => excluded.retain(|excluded| *excluded != channel_id),
+            }
+
+            settings
+                .set_value(guild_id, "channels.exclude", json!(excluded))
+                .map(|changes| {
+                    let response = match exclude {
+                        true => {
+                            format!(
+                                "Mentions in {} are no longer counted.",
+                                channel_id.mention()
+                            )
+                        }
+                        false => format!("Mentions in {} are counted again.", channel_id.mention()),
+                    };
+                    (response, changes)
+                })
+        }
+        "announcements" => {
+            let mut values = Vec::new();
+            if let Some(CommandDataOptionValue::Boolean(milestones)) =
+                find_option(&subcommand.options, "milestones")
+            {
+                values.push(("announce_milestones", json!(milestones)));
+            }
+            if let Some(records) = string(subcommand, "records") {
+                values.push(("announce_records", json!(records)));
+            }
+            settings
+                .set_values(guild_id, &values)
+                .map(|changes| ("Updated the announcements.".to_string(), changes))
+        }
+        "admin-role" => match find_option(&subcommand.options, "role") {
+            Some(CommandDataOptionValue::Role(role)) => settings
+                .set_value(guild_id, "admin_role", json!(role.id))
+                .map(|changes| {
+                    let response =
+                        format!("Members of {} can now manage the bot.", role.id.mention());
+                    (response, changes)
+                }),
+            _ => settings
+                .set_value(guild_id, "admin_role", Value::Null)
+                .map(|changes| {
+                    let response = "Only server managers can manage the bot now.".to_string();
+                    (response, changes)
+                }),
+        },
+        "mod-log" => match find_option(&subcommand.options, "channel") {
+            Some(CommandDataOptionValue::Channel(channel)) => settings
+                .set_value(guild_id, "mod_log_channel", json!(channel.id))
+                .map(|changes| {
+                    let response =
+                        format!("The audit log is now mirrored to {}.", channel.id.mention());
+                    (response, changes)
+                }),
+            _ => settings
+                .set_value(guild_id, "mod_log_channel", Value::Null)
+                .map(|changes| {
+                    let response = "The audit log is no longer mirrored.".to_string();
+                    (response, changes)
+                }),
+        },
+        "set" => {
+            let key = string(subcommand, "key").unwrap_or_default();
+            let value = string(subcommand, "value").unwrap_or_default();
+            settings
+                .set(guild_id, key, value)
+                .map(|changes| (format!("Updated `{}`.", key), changes))
+        }
+        _ => return (String::new(), Vec::new()),
+    };
+
+    match result {
+        Ok(result) => result,
+        Err(SettingsError::Invalid(reason)) => (reason, Vec::new()),
+        Err(e) => {
+            tracing::error!(
+                "An error occurred saving the settings of {}: {}",
+                guild_id,
+                e
+            );
+            let response = "The settings could not be saved, please try again later.".to_string();
+            (response, Vec::new())
+        }
+    }
+}
+
+/// Summarizes the settings that can be changed through their own subcommands.
+fn show(settings: &GuildSettings) -> String {
+    let list = |items: Vec<String>| match items.is_empty() {
+        true => "none".to_string(),
+        false => items.join(", "),
+    };
+    let keywords = |keywords: &[KeywordRule]| {
+        list(
+            keywords
+                .iter()
+                .map(|keyword| match keyword.mode {
+                    MatchMode::WholeWord => format!("`{}`", keyword.pattern),
+                    MatchMode::Substring => format!("`{}` (substring)", keyword.pattern),
+                })
+                .collect(),
+        )
+    };
+
+    let report_channel = match settings.report_channel {
+        Some(channel_id) => channel_id.mention().to_string(),
+        None => format!("the channel named `{}`", settings.report_channel_name),
+    };
+    let records = match settings.announce_records {
+        AnnounceLevel::Server => "server records",
+        AnnounceLevel::Channel => "channel records",
+        AnnounceLevel::Both => "server and channel records",
+        AnnounceLevel::Off => "no records",
+    };
+    let milestones = match settings.announce_milestones {
+        true => "milestones",
+        false => "no milestones",
+    };
+    let admin_role = match settings.admin_role {
+        Some(role_id) => role_id.mention().to_string(),
+        None => "none".to_string(),
+    };
+
+    let mod_log = match settings.mod_log_channel {
+        Some(channel_id) => channel_id.mention().to_string(),
+        None => "none".to_string(),
+    };
+
+    format!(
+        "**Report channel:** {}\n\
+         **Report schedule:** `{}` in {}\n\
+         **Keywords:** {}\n\
+         **Excluded keywords:** {}\n\
+         **Excluded channels:** {}\n\
+         **Announcements:** {} and {}\n\
+         **Admin role:** {}\n\
+         **Mod log:** {}\n\n\
+         Any other setting can be changed through `/crabe config set`.",
+        report_channel,
+        settings.report_schedule,
+        settings.timezone,
+        keywords(&settings.keywords.include),
+        keywords(&settings.keywords.exclude),
+        list(
+            settings
+                .channels
+                .exclude
+                .iter()
+                .map(|channel_id| channel_id.mention().to_string())
+                .collect()
+        ),
+        milestones,
+        records,
+        admin_role,
+        mod_log,
+    )
+}
+
+fn string<'a>(subcommand: &'a CommandDataOption, name: &str) -> Option<&'a str> {
+    match find_option(&subcommand.options, name) {
+        Some(CommandDataOptionValue::String(value)) => Some(value.as_str()),
+        _ => None,
+    }
+}
+
+/// The setting holding either the included or the excluded keywords, and its current value.
+fn keywords(
+    settings: &GuildSettings,
+    subcommand: &CommandDataOption,
+) -> (&'static str, Vec<KeywordRule>) {
+    match find_option(&subcommand.options, "exclude") {
+        Some(CommandDataOptionValue::Boolean(true)) => {
+            ("keywords.exclude", settings.keywords.exclude.clone())
+        }
+        _ => ("keywords.include", settings.keywords.include.clone()),
+    }
+}
@@ -0,0 +1,78 @@
+use chrono::{DateTime, Utc};
+use serde_json::json;
+use serenity::{
+    builder::CreateApplicationCommandOption,
+    model::{
+        application::{
+            command::CommandOptionType,
+            interaction::application_command::{CommandDataOption, CommandDataOptionValue},
+        },
+        prelude::{GuildId, Mentionable, UserId},
+    },
+};
+
+use crate::{
+    audit::{AuditAction, AuditEntry},
+    commands::find_option,
+    state::GuildStates,
+};
+
+pub fn register(
+    subcommand: &mut CreateApplicationCommandOption,
+) -> &mut CreateApplicationCommandOption {
+    subcommand
+        .name("count")
+        .description("Corrects how many times a member has mentioned Rust")
+        .kind(CommandOptionType::SubCommand)
+        .create_sub_option(|option| {
+            option
+                .name("user")
+                .description("The member whose count to correct")
+                .kind(CommandOptionType::User)
+                .required(true)
+        })
+        .create_sub_option(|option| {
+            option
+                .name("count")
+                .description("The new count, resetting it to zero by default")
+                .kind(CommandOptionType::Integer)
+                .min_int_value(0)
+        })
+}
+
+/// Changes the all-time mention count of a user, returning the response to show to the admin
+/// along with the entry for the audit log.
+pub async fn run(
+    states: &GuildStates,
+    guild_id: GuildId,
+    actor_id: UserId,
+    subcommand: &CommandDataOption,
+    now: DateTime<Utc>,
+) -> (String, Option<AuditEntry>) {
+    let user_id = match find_option(&subcommand.options, "user") {
+        Some(CommandDataOptionValue::User(user, _)) => user.id,
+        _ => return ("Please choose a member.".to_string(), None),
+    };
+    let count = match find_option(&subcommand.options, "count") {
+        Some(CommandDataOptionValue::Integer(count)) => (*count).max(0) as usize,
+        _ => 0,
+    };
+
+    let previous = states.set_mention_count(guild_id, user_id, count).await;
+    let response = format!(
+        "The mention count of {} is now {} instead of {}.",
+        user_id.mention(),
+        count,
+        previous
+    );
+    let entry = AuditEntry {
+        guild_id,
+        actor_id,
+        action: AuditAction::CountChange,
+        target: user_id.to_string(),
+        old_value: json!(previous),
+        new_value: json!(count),
+        at: now,
+    };
+    (response, Some(entry))
+}
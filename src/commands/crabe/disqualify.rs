@@ -0,0 +1,109 @@
+use chrono::{DateTime, Utc};
+use serde_json::{json, Map, Value};
+use serenity::{
+    builder::CreateApplicationCommandOption,
+    model::{
+        application::{
+            command::CommandOptionType,
+            interaction::application_command::{CommandDataOption, CommandDataOptionValue},
+        },
+        prelude::{GuildId, MessageId, UserId},
+    },
+};
+
+use crate::{
+    audit::{AuditAction, AuditEntry},
+    commands::find_option,
+    state::GuildStates,
+};
+
+pub fn register(
+    subcommand: &mut CreateApplicationCommandOption,
+) -> &mut CreateApplicationCommandOption {
+    subcommand
+        .name("disqualify")
+        .description("Takes back the mentions of a message that shouldn't have counted")
+        .kind(CommandOptionType::SubCommand)
+        .create_sub_option(|option| {
+            option
+                .name("message")
+                .description("The ID of the message or a link to it")
+                .kind(CommandOptionType::String)
+                .required(true)
+        })
+}
+
+/// Disqualifies a message, returning the response to show to the admin along with the entry for
+/// the audit log.
+pub async fn run(
+    states: &GuildStates,
+    guild_id: GuildId,
+    actor_id: UserId,
+    subcommand: &CommandDataOption,
+    now: DateTime<Utc>,
+) -> (String, Option<AuditEntry>) {
+    let message = match find_option(&subcommand.options, "message") {
+        Some(CommandDataOptionValue::String(message)) => message.as_str(),
+        _ => "",
+    };
+    let message_id = match parse_message(message, guild_id) {
+        Some(message_id) => message_id,
+        None => {
+            let response = format!(
+                "`{}` is neither the ID of a message nor a link to one on this server.",
+                message
+            );
+            return (response, None);
+        }
+    };
+
+    let disqualified = match states.disqualify(guild_id, message_id).await {
+        Some(disqualified) => disqualified,
+        None => {
+            let response = "No mentions of Rust were counted for that message.".to_string();
+            return (response, None);
+        }
+    };
+
+    let mentions = disqualified.weights.values().sum::<usize>();
+    let response = format!(
+        "Disqualified the message, taking back {} {}.",
+        mentions,
+        if mentions == 1 { "mention" } else { "mentions" }
+    );
+    let weights = disqualified
+        .weights
+        .iter()
+        .map(|(user_id, weight)| (user_id.to_string(), json!(weight)))
+        .collect::<Map<_, _>>();
+    let entry = AuditEntry {
+        guild_id,
+        actor_id,
+        action: AuditAction::Disqualification,
+        target: message_id.to_string(),
+        old_value: json!({
+            "channel_id": disqualified.channel_id.to_string(),
+            "mentions": weights,
+        }),
+        new_value: Value::Null,
+        at: now,
+    };
+    (response, Some(entry))
+}
+
+/// Reads the ID of a message, either given as is or as the last part of a link to it such as
+/// `https://discord.com/channels/<guild>/<channel>/<message>`, which has to point to this guild.
+fn parse_message(message: &str, guild_id: GuildId) -> Option<MessageId> {
+    let parts = message
+        .trim()
+        .trim_end_matches('/')
+        .split('/')
+        .collect::<Vec<_>>();
+    match parts.as_slice() {
+        [message_id] => message_id.parse().ok().map(MessageId),
+        [.., "channels", guild, _, message_id] if *guild == guild_id.to_string() => {
+            message_id.parse().ok().map(MessageId)
+        }
+        _ => None,
+    }
+}
@@ -0,0 +1,177 @@
+pub mod audit;
+pub mod config;
+pub mod count;
+pub mod disqualify;
+pub mod rebuild;
+pub mod record;
+
+use chrono::{DateTime, Utc};
+use serenity::{
+    builder::CreateApplicationCommand,
+    client::Context,
+    model::application::interaction::{
+        application_command::ApplicationCommandInteraction, InteractionResponseType,
+    },
+};
+
+use crate::{
+    audit::{AuditAction, AuditEntry},
+    settings::{GuildSettings, Settings},
+    state::GuildStates,
+    storage::Storage,
+};
+
+/// The response to a subcommand the bot doesn't know, e.g. because its commands are outdated.
+const UNKNOWN_SUBCOMMAND: &str = "This subcommand doesn't exist anymore.";
+
+pub fn register(command: &mut CreateApplicationCommand) -> &mut CreateApplicationCommand {
+    command
+        .name("crabe")
+        .description("Manages the bot on this server")
+        .dm_permission(false)
+        .create_option(config::register)
+        .create_option(audit::register)
+        .create_option(count::register)
+        .create_option(record::register)
+        .create_option(disqualify::register)
+        .create_option(rebuild::register)
+}
+
+pub async fn run(
+    context: &Context,
+    storage: &dyn Storage,
+    states: &GuildStates,
+    settings: &Settings,
+    command: &ApplicationCommandInteraction,
+    now: DateTime<Utc>,
+) -> serenity::Result<()> {
+    let guild_id = match command.guild_id {
+        Some(guild_id) => guild_id,
+        None => return Ok(()),
+    };
+
+    // Rebuilding the counts and records can take longer than Discord waits for a response, so
+    // the response is deferred and filled in once the subcommand is done.
+    if let Err(e) = command
+        .create_interaction_response(&context.http, |r| {
+            r.kind(InteractionResponseType::DeferredChannelMessageWithSource)
+                .interaction_response_data(|d| d.ephemeral(true))
+        })
+        .await
+    {
+        tracing::error!("An error occurred deferring the response to /crabe: {}", e);
+    }
+
+    let mut entries = Vec::new();
+    let content = match settings.load(guild_id) {
+        Ok(current) if !is_admin(command, &current) => {
+            "You need the Manage Server permission or the admin role of the bot to use this command."
+                .to_string()
+        }
+        Ok(current) => match command.data.options.first() {
+            Some(group) if group.name == "config" => match group.options.first() {
+                Some(subcommand) => {
+                    let (response, changes) =
+                        config::run(settings, guild_id, &current, subcommand);
+                    entries.extend(changes.into_iter().map(|change| AuditEntry {
+                        guild_id,
+                        actor_id: command.user.id,
+                        action: AuditAction::ConfigChange,
+                        target: change.key,
+                        old_value: change.old_value,
+                        new_value: change.new_value,
+                        at: now,
+                    }));
+                    response
+                }
+                None => UNKNOWN_SUBCOMMAND.to_string(),
+            },
+            Some(subcommand) if subcommand.name == "audit" => {
+                audit::run(storage, guild_id, subcommand)
+            }
+            Some(subcommand) if subcommand.name == "count" => {
+                let (response, entry) =
+                    count::run(states, guild_id, command.user.id, subcommand, now).await;
+                entries.extend(entry);
+                response
+            }
+            Some(group) if group.name == "record" => match group.options.first() {
+                Some(subcommand) => {
+                    let (response, entry) =
+                        record::run(states, guild_id, command.user.id, subcommand, now).await;
+                    entries.extend(entry);
+                    response
+                }
+                None => UNKNOWN_SUBCOMMAND.to_string(),
+            },
+            Some(subcommand) if subcommand.name == "disqualify" => {
+                let (response, entry) =
+                    disqualify::run(states, guild_id, command.user.id, subcommand, now).await;
+                entries.extend(entry);
+                response
+            }
+            Some(subcommand) if subcommand.name == "rebuild" => {
+                let (response, entry) =
+                    rebuild::run(states, guild_id, command.user.id, subcommand, now).await;
+                entries.extend(entry);
+                response
+            }
+            _ => UNKNOWN_SUBCOMMAND.to_string(),
+        },
+        Err(e) => {
+            tracing::error!(
+                "An error occurred loading the settings of {}: {}",
+                guild_id,
+                e
+            );
+            "The settings could not be loaded, please try again later.".to_string()
+        }
+    };
+
+    // The entries are saved before responding, so the changes are audited even if the response
+    // fails.
+    for entry in &entries {
+        crate::audit::save(storage, entry);
+    }
+
+    if let Err(e) = command
+        .edit_original_interaction_response(&context.http, |r| r.content(content))
+        .await
+    {
+        tracing::error!("An error occurred responding to /crabe: {}", e);
+    }
+
+    if !entries.is_empty() {
+        // The entries are mirrored to the mod log as configured after the changes, so changing
+        // the mod log channel is already mirrored to the new one.
+        let current = settings.load(guild_id).unwrap_or_else(|e| {
+            tracing::error!(
+                "An error occurred loading the settings of {}: {}",
+                guild_id,
+                e
+            );
+            GuildSettings::default()
+        });
+        for entry in &entries {
+            crate::audit::mirror(context, &current, entry).await;
+        }
+    }
+
+    Ok(())
+}
+
+/// Whether the member using a command may manage the bot, either through the Manage Server
+/// permission or the admin role configured for the guild.
+fn is_admin(command: &ApplicationCommandInteraction, settings: &GuildSettings) -> bool {
+    let member = match &command.member {
+        Some(member) => member,
+        None => return false,
+    };
+
+    member
+        .permissions
+        .is_some_and(|permissions| permissions.manage_guild())
+        || settings
+            .admin_role
+            .is_some_and(|role| member.roles.contains(&role))
+}
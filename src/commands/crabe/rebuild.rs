@@ -0,0 +1,80 @@
+use chrono::{DateTime, Utc};
+use serde_json::{json, Value};
+use serenity::{
+    builder::CreateApplicationCommandOption,
+    model::{
+        application::{
+            command::CommandOptionType,
+            interaction::application_command::{CommandDataOption, CommandDataOptionValue},
+        },
+        prelude::{GuildId, UserId},
+    },
+};
+
+use crate::{
+    audit::{AuditAction, AuditEntry},
+    commands::find_option,
+    state::GuildStates,
+};
+
+pub fn register(
+    subcommand: &mut CreateApplicationCommandOption,
+) -> &mut CreateApplicationCommandOption {
+    subcommand
+        .name("rebuild")
+        .description("Rebuilds the counts and records from the log of all mentions")
+        .kind(CommandOptionType::SubCommand)
+        .create_sub_option(|option| {
+            option
+                .name("rule")
+                .description("The pattern of a keyword rule whose past mentions are disqualified")
+                .kind(CommandOptionType::String)
+        })
+}
+
+/// Rebuilds the counts and records of a guild, returning the response to show to the admin along
+/// with the entry for the audit log.
+pub async fn run(
+    states: &GuildStates,
+    guild_id: GuildId,
+    actor_id: UserId,
+    subcommand: &CommandDataOption,
+    now: DateTime<Utc>,
+) -> (String, Option<AuditEntry>) {
+    let rule = match find_option(&subcommand.options, "rule") {
+        Some(CommandDataOptionValue::String(rule)) => Some(rule.trim().to_string()),
+        _ => None,
+    };
+
+    let disqualified = match states.rebuild(guild_id, rule.clone()).await {
+        Some(disqualified) => disqualified,
+        None => {
+            let response = "The mention log could not be read, please try again later.".to_string();
+            return (response, None);
+        }
+    };
+
+    let response = match &rule {
+        Some(rule) => format!(
+            "Disqualified {} {} matching `{}` and rebuilt the counts and records.",
+            disqualified,
+            if disqualified == 1 {
+                "mention"
+            } else {
+                "mentions"
+            },
+            rule
+        ),
+        None => "Rebuilt the counts and records from the mention log.".to_string(),
+    };
+    let entry = AuditEntry {
+        guild_id,
+        actor_id,
+        action: AuditAction::Rebuild,
+        target: rule.unwrap_or_default(),
+        old_value: Value::Null,
+        new_value: json!(disqualified),
+        at: now,
+    };
+    (response, Some(entry))
+}
@@ -0,0 +1,114 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use crabe_core::humanize::humanize;
+use serde_json::{json, Value};
+use serenity::{
+    builder::CreateApplicationCommandOption,
+    model::{
+        application::{
+            command::CommandOptionType,
+            interaction::application_command::{CommandDataOption, CommandDataOptionValue},
+        },
+        prelude::{GuildId, Mentionable, UserId},
+    },
+};
+
+use crate::{
+    audit::{AuditAction, AuditEntry},
+    commands::find_option,
+    state::GuildStates,
+};
+
+pub fn register(group: &mut CreateApplicationCommandOption) -> &mut CreateApplicationCommandOption {
+    group
+        .name("record")
+        .description("Corrects the longest time this server, or a channel, went without Rust")
+        .kind(CommandOptionType::SubCommandGroup)
+        .create_sub_option(|subcommand| {
+            subcommand
+                .name("set")
+                .description("Sets the record, keeping the ongoing streak")
+                .kind(CommandOptionType::SubCommand)
+                .create_sub_option(|option| unit(option, "days"))
+                .create_sub_option(|option| unit(option, "hours"))
+                .create_sub_option(|option| unit(option, "minutes"))
+                .create_sub_option(channel)
+        })
+        .create_sub_option(|subcommand| {
+            subcommand
+                .name("clear")
+                .description("Clears the record, so the ongoing streak beats it")
+                .kind(CommandOptionType::SubCommand)
+                .create_sub_option(channel)
+        })
+}
+
+fn unit<'a>(
+    option: &'a mut CreateApplicationCommandOption,
+    name: &str,
+) -> &'a mut CreateApplicationCommandOption {
+    option
+        .name(name)
+        .description(format!("The number of {} the record lasts", name))
+        .kind(CommandOptionType::Integer)
+        .min_int_value(0)
+}
+
+fn channel(option: &mut CreateApplicationCommandOption) -> &mut CreateApplicationCommandOption {
+    option
+        .name("channel")
+        .description("The channel whose record to correct instead of the one of the server")
+        .kind(CommandOptionType::Channel)
+}
+
+/// Sets or clears a record, returning the response to show to the admin along with the entry for
+/// the audit log.
+pub async fn run(
+    states: &GuildStates,
+    guild_id: GuildId,
+    actor_id: UserId,
+    subcommand: &CommandDataOption,
+    now: DateTime<Utc>,
+) -> (String, Option<AuditEntry>) {
+    let channel_id = match find_option(&subcommand.options, "channel") {
+        Some(CommandDataOptionValue::Channel(channel)) => Some(channel.id),
+        _ => None,
+    };
+    // A record of zero is beaten by any streak, so it's cleared instead.
+    let duration = match subcommand.name.as_str() {
+        "set" => {
+            let amount = |name, seconds| match find_option(&subcommand.options, name) {
+                Some(CommandDataOptionValue::Integer(amount)) => (*amount).max(0) as u64 * seconds,
+                _ => 0,
+            };
+            Some(Duration::from_secs(
+                amount("days", 86400) + amount("hours", 3600) + amount("minutes", 60),
+            ))
+            .filter(|duration| !duration.is_zero())
+        }
+        _ => None,
+    };
+
+    let previous = states.set_record(guild_id, channel_id, duration).await;
+    let place = match channel_id {
+        Some(channel_id) => format!("of {}", channel_id.mention()),
+        None => "of this server".to_string(),
+    };
+    let response = match duration {
+        Some(duration) => format!("The record {} is now {}.", place, humanize(duration)),
+        None => format!("Cleared the record {}.", place),
+    };
+    let entry = AuditEntry {
+        guild_id,
+        actor_id,
+        action: AuditAction::RecordChange,
+        target: channel_id
+            .map(|channel_id| channel_id.to_string())
+            .unwrap_or_default(),
+        old_value: previous.map_or(Value::Null, |previous| json!(previous.as_secs())),
+        new_value: duration.map_or(Value::Null, |duration| json!(duration.as_secs())),
+        at: now,
+    };
+    (response, Some(entry))
+}
@@ -0,0 +1,298 @@
+use std::{collections::HashMap, fmt, str::FromStr};
+
+use chrono::{DateTime, TimeZone, Utc};
+use crabe_core::leaderboard::{self, Window, PAGE_SIZE};
+use serenity::{
+    builder::{CreateApplicationCommand, CreateComponents, CreateEmbed},
+    client::Context,
+    model::{
+        application::{
+            command::CommandOptionType,
+            component::ButtonStyle,
+            interaction::{
+                application_command::{ApplicationCommandInteraction, CommandDataOptionValue},
+                message_component::MessageComponentInteraction,
+                InteractionResponseType,
+            },
+        },
+        prelude::{GuildId, UserId},
+    },
+    utils::MessageBuilder,
+};
+
+use crate::{commands::option, settings::EmbedStyle, state::GuildStates, storage::Storage};
+
+const CUSTOM_ID_PREFIX: &str = "leaderboard";
+
+/// Identifies a page of a leaderboard, encoded into the custom ID of the pagination buttons.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PageRequest {
+    pub window: Window,
+    pub page: usize,
+}
+
+impl PageRequest {
+    pub fn with_page(&self, page: usize) -> Self {
+        Self {
+            window: self.window,
+            page,
+        }
+    }
+}
+
+impl fmt::Display for PageRequest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.window {
+            Window::AllTime => write!(f, "{}:all:{}", CUSTOM_ID_PREFIX, self.page),
+            Window::Month => write!(f, "{}:month:{}", CUSTOM_ID_PREFIX, self.page),
+            Window::Week => write!(f, "{}:week:{}", CUSTOM_ID_PREFIX, self.page),
+            Window::Custom { from, to } => write!(
+                f,
+                "{}:custom:{}:{}:{}",
+                CUSTOM_ID_PREFIX,
+                self.page,
+                from.timestamp(),
+                to.timestamp()
+            ),
+        }
+    }
+}
+
+impl FromStr for PageRequest {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split(':');
+        if parts.next() != Some(CUSTOM_ID_PREFIX) {
+            return Err(());
+        }
+
+        let kind = parts.next().ok_or(())?;
+        let page = parts.next().ok_or(())?.parse().map_err(|_| ())?;
+        let mut timestamp = || {
+            parts
+                .next()
+                .and_then(|part| part.parse().ok())
+                .and_then(|seconds| Utc.timestamp_opt(seconds, 0).single())
+                .ok_or(())
+        };
+
+        let window = match kind {
+            "all" => Window::AllTime,
+            "month" => Window::Month,
+            "week" => Window::Week,
+            "custom" => Window::Custom {
+                from: timestamp()?,
+                to: timestamp()?,
+            },
+            _ => return Err(()),
+        };
+
+        Ok(Self { window, page })
+    }
+}
+
+pub fn register(command: &mut CreateApplicationCommand) -> &mut CreateApplicationCommand {
+    command
+        .name("leaderboard")
+        .description("Shows who has mentioned Rust the most on this server")
+        .dm_permission(false)
+        .create_option(|option| {
+            option
+                .name("window")
+                .description("The period of time to count the mentions in")
+                .kind(CommandOptionType::String)
+                .add_string_choice("All time", "all")
+                .add_string_choice("This month", "month")
+                .add_string_choice("This week", "week")
+                .add_string_choice("Custom date range", "custom")
+        })
+        .create_option(|option| {
+            option
+                .name("from")
+                .description("The first day of a custom date range (YYYY-MM-DD)")
+                .kind(CommandOptionType::String)
+        })
+        .create_option(|option| {
+            option
+                .name("to")
+                .description("The last day of a custom date range (YYYY-MM-DD)")
+                .kind(CommandOptionType::String)
+        })
+        .create_option(|option| {
+            option
+                .name("page")
+                .description("The page of the leaderboard to show")
+                .kind(CommandOptionType::Integer)
+                .min_int_value(1)
+        })
+}
+
+pub async fn run(
+    context: &Context,
+    storage: &dyn Storage,
+    states: &GuildStates,
+    command: &ApplicationCommandInteraction,
+    style: &EmbedStyle,
+    now: DateTime<Utc>,
+) -> serenity::Result<()> {
+    let guild_id = match command.guild_id {
+        Some(guild_id) => guild_id,
+        None => return Ok(()),
+    };
+
+    let request = match parse_request(command) {
+        Ok(request) => request,
+        Err(reason) => {
+            return command
+                .create_interaction_response(&context.http, |r| {
+                    r.kind(InteractionResponseType::ChannelMessageWithSource)
+                        .interaction_response_data(|d| d.content(reason).ephemeral(true))
+                })
+                .await;
+        }
+    };
+
+    let (embed, components) = render(context, storage, states, guild_id, request, style, now).await;
+    command
+        .create_interaction_response(&context.http, |r| {
+            r.kind(InteractionResponseType::ChannelMessageWithSource)
+                .interaction_response_data(|d| d.set_embed(embed).set_components(components))
+        })
+        .await
+}
+
+pub async fn paginate(
+    context: &Context,
+    storage: &dyn Storage,
+    states: &GuildStates,
+    component: &MessageComponentInteraction,
+    request: PageRequest,
+    style: &EmbedStyle,
+    now: DateTime<Utc>,
+) -> serenity::Result<()> {
+    let guild_id = match component.guild_id {
+        Some(guild_id) => guild_id,
+        None => return Ok(()),
+    };
+
+    let (embed, components) = render(context, storage, states, guild_id, request, style, now).await;
+    component
+        .create_interaction_response(&context.http, |r| {
+            r.kind(InteractionResponseType::UpdateMessage)
+                .interaction_response_data(|d| d.set_embed(embed).set_components(components))
+        })
+        .await
+}
+
+fn parse_request(command: &ApplicationCommandInteraction) -> Result<PageRequest, String> {
+    let string = |name| match option(command, name) {
+        Some(CommandDataOptionValue::String(value)) => Some(value.as_str()),
+        _ => None,
+    };
+
+    let window = match string("window") {
+        None | Some("all") => Window::AllTime,
+        Some("month") => Window::Month,
+        Some("week") => Window::Week,
+        Some(_) => match (string("from"), string("to")) {
+            (Some(from), Some(to)) => Window::custom(from, to)?,
+            _ => {
+                return Err(
+                    "Please provide both a `from` and a `to` date for a custom date range."
+                        .to_string(),
+                )
+            }
+        },
+    };
+
+    let page = match option(command, "page") {
+        Some(CommandDataOptionValue::Integer(page)) => (*page).max(1) as usize,
+        _ => 1,
+    };
+
+    Ok(PageRequest { window, page })
+}
+
+async fn load_counts(
+    storage: &dyn Storage,
+    states: &GuildStates,
+    guild_id: GuildId,
+    window: Window,
+    now: DateTime<Utc>,
+) -> HashMap<UserId, usize> {
+    match window.bounds(now) {
+        None => states.mention_counts(guild_id).await,
+        Some((from, to)) => storage
+            .load_mention_counts_between(guild_id, from, to)
+            .unwrap_or_else(|e| {
+                tracing::error!("An error occurred loading the mention counts: {}", e);
+                HashMap::new()
+            }),
+    }
+}
+
+async fn render(
+    context: &Context,
+    storage: &dyn Storage,
+    states: &GuildStates,
+    guild_id: GuildId,
+    request: PageRequest,
+    style: &EmbedStyle,
+    now: DateTime<Utc>,
+) -> (CreateEmbed, CreateComponents) {
+    let ranking =
+        leaderboard::rank(load_counts(storage, states, guild_id, request.window, now).await);
+    let pages = leaderboard::page_count(ranking.len());
+    let page = request.page.min(pages);
+    let offset = (page - 1) * PAGE_SIZE;
+
+    let mut message_builder = MessageBuilder::new();
+    if ranking.is_empty() {
+        message_builder.push("Nobody has mentioned Rust in this period. Impressive!");
+    }
+
+    for (index, (user_id, count)) in ranking.iter().enumerate().skip(offset).take(PAGE_SIZE) {
+        let name = match user_id.to_user(context).await {
+            Ok(user) => user.nick_in(context, guild_id).await.unwrap_or(user.name),
+            Err(_) => format!("Unknown user {}", user_id),
+        };
+        message_builder.push(format!(
+            "{}. **{}**: {} mention{}\n",
+            index + 1,
+            name,
+            count,
+            if *count == 1 { "" } else { "s" }
+        ));
+    }
+
+    let mut embed = CreateEmbed::default();
+    embed
+        .title(format!(
+            "🦀 Rust Leaderboard: {} 🦀",
+            request.window.title()
+        ))
+        .description(message_builder.build())
+        .color(style.color)
+        .footer(|f| f.text(format!("Page {} of {} • {}", page, pages, style.footer)));
+
+    let mut components = CreateComponents::default();
+    if pages > 1 {
+        components.create_action_row(|row| {
+            row.create_button(|b| {
+                b.custom_id(request.with_page(page.saturating_sub(1).max(1)))
+                    .label("Previous")
+                    .style(ButtonStyle::Secondary)
+                    .disabled(page == 1)
+            })
+            .create_button(|b| {
+                b.custom_id(request.with_page((page + 1).min(pages)))
+                    .label("Next")
+                    .style(ButtonStyle::Secondary)
+                    .disabled(page == pages)
+            })
+        });
+    }
+
+    (embed, components)
+}
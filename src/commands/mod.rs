@@ -0,0 +1,26 @@
+pub mod crabe;
+pub mod leaderboard;
+pub mod since;
+
+use serenity::model::application::interaction::application_command::{
+    ApplicationCommandInteraction, CommandDataOption, CommandDataOptionValue,
+};
+
+/// Looks up the resolved value of a top-level option of a slash command.
+pub fn option<'a>(
+    command: &'a ApplicationCommandInteraction,
+    name: &str,
+) -> Option<&'a CommandDataOptionValue> {
+    find_option(&command.data.options, name)
+}
+
+/// Looks up the resolved value of an option among others, such as those of a subcommand.
+pub fn find_option<'a>(
+    options: &'a [CommandDataOption],
+    name: &str,
+) -> Option<&'a CommandDataOptionValue> {
+    options
+        .iter()
+        .find(|option| option.name == name)
+        .and_then(|option| option.resolved.as_ref())
+}
@@ -0,0 +1,94 @@
+use chrono::{DateTime, Utc};
+use crabe_core::humanize::humanize;
+use serenity::{
+    builder::CreateApplicationCommand,
+    client::Context,
+    model::{
+        application::{
+            command::CommandOptionType,
+            interaction::{
+                application_command::{ApplicationCommandInteraction, CommandDataOptionValue},
+                InteractionResponseType,
+            },
+        },
+        prelude::Mentionable,
+    },
+};
+
+use crate::{commands::option, settings::EmbedStyle, state::GuildStates};
+
+pub fn register(command: &mut CreateApplicationCommand) -> &mut CreateApplicationCommand {
+    command
+        .name("since")
+        .description("Shows how long this server has gone without mentioning Rust")
+        .dm_permission(false)
+        .create_option(|option| {
+            option
+                .name("channel")
+                .description("The channel to show the record of instead of the whole server")
+                .kind(CommandOptionType::Channel)
+        })
+}
+
+pub async fn run(
+    context: &Context,
+    states: &GuildStates,
+    command: &ApplicationCommandInteraction,
+    style: &EmbedStyle,
+    now: DateTime<Utc>,
+) -> serenity::Result<()> {
+    let guild_id = match command.guild_id {
+        Some(guild_id) => guild_id,
+        None => return Ok(()),
+    };
+
+    let channel_id = match option(command, "channel") {
+        Some(CommandDataOptionValue::Channel(channel)) => Some(channel.id),
+        _ => None,
+    };
+
+    let record = states.record(guild_id, channel_id).await;
+
+    let place = match channel_id {
+        Some(channel_id) => format!("in {}", channel_id.mention()),
+        None => "on this server".to_string(),
+    };
+
+    let description = match record.last_mention {
+        None => format!("Nobody has mentioned Rust {} yet. Keep it up!", place),
+        Some(last_mention) => {
+            let current = (now - last_mention).to_std().unwrap_or_default();
+            let record = record.duration.unwrap_or_default();
+
+            let mut description = format!(
+                "It has been {} since somebody last mentioned Rust {}.\n\nThe record {} is {}.",
+                humanize(current),
+                place,
+                place,
+                humanize(record)
+            );
+            match record.checked_sub(current) {
+                Some(remaining) if !remaining.is_zero() => description.push_str(&format!(
+                    " Hold out for another {} to beat it!",
+                    humanize(remaining)
+                )),
+                _ => description.push_str(" You are setting a new record right now!"),
+            }
+            description
+        }
+    };
+
+    command
+        .create_interaction_response(&context.http, |r| {
+            r.kind(InteractionResponseType::ChannelMessageWithSource)
+                .interaction_response_data(|d| {
+                    d.embed(|e| {
+                        e.title("🦀 How long has it been? 🦀")
+                            .description(description)
+                            .color(style.color)
+                            .footer(|f| f.text(&style.footer))
+                    })
+                })
+        })
+        .await
+}
@@ -0,0 +1,191 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt, fs, io,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+
+use serde::Deserialize;
+use serenity::model::prelude::GuildId;
+
+use crate::settings::{GuildSettings, Overrides, Settings};
+
+/// How often the config file is checked for changes.
+const WATCH_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(io::Error),
+    Toml(toml::de::Error),
+    /// A section of the config file doesn't hold valid settings.
+    Invalid {
+        section: String,
+        reason: String,
+    },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io(e) => write!(f, "I/O error: {}", e),
+            ConfigError::Toml(e) => write!(f, "TOML error: {}", e),
+            ConfigError::Invalid { section, reason } => {
+                write!(f, "Invalid settings in [{}]: {}", section, reason)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl From<io::Error> for ConfigError {
+    fn from(e: io::Error) -> Self {
+        ConfigError::Io(e)
+    }
+}
+
+impl From<toml::de::Error> for ConfigError {
+    fn from(e: toml::de::Error) -> Self {
+        ConfigError::Toml(e)
+    }
+}
+
+/// The layout of the config file.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigFile {
+    #[serde(default)]
+    defaults: Overrides,
+    #[serde(default)]
+    guilds: BTreeMap<String, Overrides>,
+}
+
+/// The config file, holding the default settings of all guilds and overrides for single ones.
+///
+/// Both are given as settings overriding the compiled defaults, e.g. `report_schedule` under
+/// `[defaults]` or under `[guilds.<guild ID>]`.
+#[derive(Clone, Debug, Default)]
+pub struct Config {
+    defaults: Overrides,
+    guilds: HashMap<GuildId, Overrides>,
+}
+
+impl Config {
+    /// Loads the config file, which falls back to the compiled defaults if it doesn't exist.
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        match fs::read_to_string(path) {
+            Ok(text) => Self::parse(&text),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Parses a config file, checking that it holds valid settings for every guild.
+    pub fn parse(text: &str) -> Result<Self, ConfigError> {
+        let file = toml::from_str::<ConfigFile>(text)?;
+
+        GuildSettings::layered(&[&file.defaults]).map_err(|reason| ConfigError::Invalid {
+            section: "defaults".to_string(),
+            reason,
+        })?;
+
+        let mut guilds = HashMap::new();
+        for (guild_id, overrides) in file.guilds {
+            let section = format!("guilds.{}", guild_id);
+            let guild_id = guild_id.parse::<u64>().map_err(|_| ConfigError::Invalid {
+                section: section.clone(),
+                reason: format!("`{}` is not the ID of a guild.", guild_id),
+            })?;
+            GuildSettings::layered(&[&file.defaults, &overrides])
+                .map_err(|reason| ConfigError::Invalid { section, reason })?;
+            guilds.insert(GuildId(guild_id), overrides);
+        }
+
+        Ok(Self {
+            defaults: file.defaults,
+            guilds,
+        })
+    }
+
+    /// The defaults followed by the overrides of a guild, if it has any.
+    pub fn layers(&self, guild_id: GuildId) -> Vec<&Overrides> {
+        let mut layers = vec![&self.defaults];
+        layers.extend(self.guilds.get(&guild_id));
+        layers
+    }
+}
+
+/// Spawns a task reloading the config file whenever it changes or the bot receives `SIGHUP`,
+/// which only exists on Unix.
+///
+/// Only the settings are replaced, so the connection to Discord is kept. An invalid config file
+/// is reported and ignored, keeping the last valid one in place.
+pub fn watch(path: PathBuf, settings: Arc<Settings>) -> io::Result<()> {
+    let mut hangups = hangups()?;
+
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(WATCH_INTERVAL);
+        let mut last_modified = modified_at(&path);
+
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {
+                    let modified = modified_at(&path);
+                    if modified == last_modified {
+                        continue;
+                    }
+                    last_modified = modified;
+                }
+                _ = hangup(&mut hangups) => {}
+            }
+
+            match Config::load(&path) {
+                Ok(config) => {
+                    settings.reload(config);
+                    tracing::info!("Reloaded the config file {}.", path.display());
+                }
+                Err(e) => tracing::error!(
+                    "The config file {} is invalid, keeping the previous one: {}",
+                    path.display(),
+                    e
+                ),
+            }
+        }
+    });
+
+    Ok(())
+}
+
+#[cfg(unix)]
+type Hangups = tokio::signal::unix::Signal;
+
+#[cfg(not(unix))]
+struct Hangups;
+
+#[cfg(unix)]
+fn hangups() -> io::Result<Hangups> {
+    tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup())
+}
+
+#[cfg(not(unix))]
+fn hangups() -> io::Result<Hangups> {
+    Ok(Hangups)
+}
+
+#[cfg(unix)]
+async fn hangup(hangups: &mut Hangups) {
+    hangups.recv().await;
+}
+
+/// Never completes, as there is no `SIGHUP` to wait for.
+#[cfg(not(unix))]
+async fn hangup(_: &mut Hangups) {
+    std::future::pending::<()>().await
+}
+
+fn modified_at(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
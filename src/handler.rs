@@ -0,0 +1,609 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use crabe_core::{
+    clock::Clock,
+    detection::{Detector, KeywordRules},
+    humanize,
+    scheduler::Scheduler,
+    sources::{strip_custom_emoji, Emoji, MentionSource},
+};
+use serenity::{
+    client::{Context, EventHandler},
+    model::{
+        application::{command::Command, interaction::Interaction},
+        channel::{Message, Reaction, ReactionType},
+        event::MessageUpdateEvent,
+        gateway::Ready,
+        prelude::{ChannelId, GuildId, MessageId, StickerItem},
+    },
+    prelude::Mentionable,
+};
+
+use crate::{
+    commands::{self, leaderboard::PageRequest},
+    jobs::{FlushJob, MilestoneJob, ReportJob, RetentionJob},
+    settings::{AnnounceLevel, EmbedStyle, GuildSettings, Settings},
+    state::{GuildStates, Mention},
+    storage::Storage,
+};
+
+pub struct Handler {
+    storage: Arc<dyn Storage>,
+    settings: Arc<Settings>,
+    clock: Arc<dyn Clock>,
+    /// How often the scheduler checks whether any of its jobs are due.
+    tick_interval: Duration,
+    scheduler_started: AtomicBool,
+    detectors: std::sync::Mutex<HashMap<GuildId, Arc<Detector>>>,
+    states: Arc<GuildStates>,
+}
+
+impl Handler {
+    pub fn new(
+        storage: Arc<dyn Storage>,
+        settings: Arc<Settings>,
+        clock: Arc<dyn Clock>,
+        tick_interval: Duration,
+        states: Arc<GuildStates>,
+    ) -> Self {
+        Self {
+            storage,
+            settings,
+            clock,
+            tick_interval,
+            scheduler_started: AtomicBool::new(false),
+            detectors: Default::default(),
+            states,
+        }
+    }
+
+    /// Returns the detector for the keyword rules of a guild, only compiling them when they changed.
+    fn detector(&self, guild_id: GuildId, rules: &KeywordRules) -> Option<Arc<Detector>> {
+        let mut detectors = self
+            .detectors
+            .lock()
+            .expect("The detector cache mutex was poisoned.");
+        if let Some(detector) = detectors.get(&guild_id) {
+            if detector.rules() == rules {
+                return Some(detector.clone());
+            }
+        }
+
+        match Detector::new(rules) {
+            Ok(detector) => {
+                let detector = Arc::new(detector);
+                detectors.insert(guild_id, detector.clone());
+                Some(detector)
+            }
+            Err(reason) => {
+                tracing::error!("The keyword rules of {} are invalid: {}", guild_id, reason);
+                None
+            }
+        }
+    }
+
+    /// Counts the mentions of a user found in a single event, ends the ongoing streak and
+    /// announces the records it beat.
+    ///
+    /// Mentions that were already saved before, e.g. because Discord replayed the event, are
+    /// skipped.
+    async fn track(
+        &self,
+        context: &Context,
+        settings: &GuildSettings,
+        name: &str,
+        mentions: Vec<Mention>,
+    ) {
+        let channel_id = match mentions.first() {
+            Some(mention) => mention.channel_id,
+            None => return,
+        };
+        // Only mentions written in a message are taken back when the message is deleted.
+        let revertible = mentions[0].source != MentionSource::Reaction;
+        let tracked = match self.states.track(mentions, revertible).await {
+            Some(tracked) => tracked,
+            None => return,
+        };
+
+        tracing::info!(
+            "{} mentioned Rust {} times so far.",
+            name,
+            tracked.mention_count
+        );
+        tracing::info!(
+            "Previous record duration was {:?}, the new record is {:?} on the server and {:?} in the channel",
+            tracked.previous,
+            tracked.beaten,
+            tracked.beaten_channel
+        );
+
+        // A server record usually is a channel record as well, so only the former is announced
+        // when both are.
+        let announce_server = matches!(
+            settings.announce_records,
+            AnnounceLevel::Server | AnnounceLevel::Both
+        );
+        let announce_channel = matches!(
+            settings.announce_records,
+            AnnounceLevel::Channel | AnnounceLevel::Both
+        );
+        let description = match (tracked.beaten, tracked.beaten_channel) {
+            (Some(current), _) if announce_server => format!(
+                "You lasted {} without mentioning Rust, that's a new record on this server!",
+                humanize::humanize(current)
+            ),
+            (_, Some(current)) if announce_channel => format!(
+                "You lasted {} without mentioning Rust in {}, that's a new record for this channel!",
+                humanize::humanize(current),
+                channel_id.mention()
+            ),
+            _ => return,
+        };
+
+        tracing::info!("New record: {}", description);
+
+        if let Err(e) = channel_id
+            .send_message(context, |m| {
+                m.embed(|e| {
+                    e.title("🦀 Did somebody say Rust? 🦀")
+                        .description(description)
+                        .color(settings.embed.color)
+                        .footer(|f| f.text(&settings.embed.footer))
+                })
+            })
+            .await
+        {
+            tracing::error!("An error occurred sending a new record message: {}", e);
+        }
+    }
+
+    /// Finds the mentions of Rust in the content and stickers of a message, copying everything
+    /// but their source, weight and rule from `base`.
+    fn find_mentions(
+        &self,
+        settings: &GuildSettings,
+        base: &Mention,
+        name: &str,
+        content: &str,
+        stickers: &[StickerItem],
+    ) -> Vec<Mention> {
+        let text = settings.regions.filter(content);
+        let mention = |source, rule| Mention {
+            source,
+            weight: settings.sources.settings(source).weight,
+            rule: Some(rule),
+            ..base.clone()
+        };
+        let mut mentions = Vec::new();
+
+        if settings.sources.text.enabled {
+            let words = strip_custom_emoji(&text);
+            if let Some(detection) = self
+                .detector(base.guild_id, &settings.keywords)
+                .and_then(|detector| detector.detect(&settings.normalization.normalize(&words)))
+            {
+                tracing::info!(
+                    "{} mentioned Rust by writing \"{}\", matching the rule `{}`.",
+                    name,
+                    detection.matched,
+                    detection.rule
+                );
+                mentions.push(mention(MentionSource::Text, detection.rule));
+            }
+        }
+
+        if settings.sources.emoji.enabled {
+            if let Some(emoji) = settings.sources.detect_emoji(&text) {
+                tracing::info!("{} mentioned Rust with {}.", name, emoji);
+                mentions.push(mention(MentionSource::Emoji, emoji));
+            }
+        }
+
+        if settings.sources.stickers.enabled {
+            if let Some(sticker) = stickers.iter().find(|sticker| {
+                settings
+                    .sources
+                    .matches_sticker(sticker.id.0, &sticker.name)
+            }) {
+                tracing::info!("{} mentioned Rust with the sticker {}.", name, sticker.name);
+                mentions.push(mention(MentionSource::Sticker, sticker.name.clone()));
+            }
+        }
+
+        mentions
+    }
+
+    /// The style of the embeds posted to a guild, falling back to the default if its settings
+    /// can't be loaded.
+    fn embed_style(&self, guild_id: Option<GuildId>) -> EmbedStyle {
+        let guild_id = match guild_id {
+            Some(guild_id) => guild_id,
+            None => return EmbedStyle::default(),
+        };
+        match self.settings.load(guild_id) {
+            Ok(settings) => settings.embed,
+            Err(e) => {
+                tracing::error!(
+                    "An error occurred loading the settings of {}: {}",
+                    guild_id,
+                    e
+                );
+                EmbedStyle::default()
+            }
+        }
+    }
+
+    /// Takes back the mentions of deleted messages if they were deleted within the grace window
+    /// of their guild, subtracting them from the counts and restoring the streaks they ended.
+    async fn revert(&self, guild_id: GuildId, message_ids: Vec<MessageId>) {
+        let settings = match self.settings.load(guild_id) {
+            Ok(settings) => settings,
+            Err(e) => {
+                tracing::error!(
+                    "An error occurred loading the settings of {}: {}",
+                    guild_id,
+                    e
+                );
+                return;
+            }
+        };
+        let cutoff =
+            self.clock.now() - chrono::Duration::seconds(settings.deletion_grace_seconds as i64);
+
+        let reverted = self.states.revert(guild_id, message_ids, cutoff).await;
+        if reverted > 0 {
+            tracing::info!(
+                "Took back the mentions of {} messages deleted within the grace window.",
+                reverted
+            );
+        }
+    }
+}
+
+#[serenity::async_trait]
+impl EventHandler for Handler {
+    async fn message(&self, context: Context, msg: Message) {
+        let guild_id = match msg.guild_id {
+            Some(guild_id) => guild_id,
+            None => return,
+        };
+
+        if msg.author.id == context.cache.current_user().id {
+            return;
+        }
+
+        let settings = match self.settings.load(guild_id) {
+            Ok(settings) => settings,
+            Err(e) => {
+                tracing::error!(
+                    "An error occurred loading the settings of {}: {}",
+                    guild_id,
+                    e
+                );
+                return;
+            }
+        };
+
+        let roles = msg
+            .member
+            .as_ref()
+            .map_or(&[][..], |member| member.roles.as_slice());
+        if settings.ignore.ignores_message(&msg, roles)
+            || !settings
+                .channels
+                .allows_channel(&context, msg.channel_id)
+                .await
+        {
+            return;
+        }
+
+        // Use the time Discord assigned to the message rather than the time it was received, so
+        // gateway lag, replayed events and restarts don't distort the tracked durations.
+        let base = Mention {
+            guild_id,
+            channel_id: msg.channel_id,
+            user_id: msg.author.id,
+            message_id: msg.id,
+            source: MentionSource::Text,
+            weight: 0,
+            rule: None,
+            sent_at: *msg.timestamp,
+        };
+        let mentions = self.find_mentions(
+            &settings,
+            &base,
+            &msg.author.name,
+            &msg.content,
+            &msg.sticker_items,
+        );
+
+        self.track(&context, &settings, &msg.author.name, mentions)
+            .await;
+    }
+
+    async fn message_update(
+        &self,
+        context: Context,
+        _old_if_available: Option<Message>,
+        new: Option<Message>,
+        event: MessageUpdateEvent,
+    ) {
+        let (guild_id, author) = match (event.guild_id, &event.author) {
+            (Some(guild_id), Some(author)) => (guild_id, author),
+            _ => return,
+        };
+
+        // Edits not touching the content, e.g. embeds being resolved, can't add any mentions.
+        if author.id == context.cache.current_user().id || event.content.is_none() {
+            return;
+        }
+
+        let settings = match self.settings.load(guild_id) {
+            Ok(settings) => settings,
+            Err(e) => {
+                tracing::error!(
+                    "An error occurred loading the settings of {}: {}",
+                    guild_id,
+                    e
+                );
+                return;
+            }
+        };
+
+        let roles = context
+            .cache
+            .member_field(guild_id, author.id, |member| member.roles.clone())
+            .unwrap_or_default();
+        let ignored = match &new {
+            Some(msg) => settings.ignore.ignores_message(msg, &roles),
+            None => settings.ignore.ignores_user(author, &roles),
+        };
+        if ignored
+            || !settings
+                .channels
+                .allows_channel(&context, event.channel_id)
+                .await
+        {
+            return;
+        }
+
+        // Mentions already found in the message before are saved, so only new ones are counted.
+        // These are counted at the time of the edit, which is when they were written.
+        let base = Mention {
+            guild_id,
+            channel_id: event.channel_id,
+            user_id: author.id,
+            message_id: event.id,
+            source: MentionSource::Text,
+            weight: 0,
+            rule: None,
+            sent_at: event
+                .edited_timestamp
+                .map_or_else(|| self.clock.now(), |edited_at| *edited_at),
+        };
+        let mentions = self.find_mentions(
+            &settings,
+            &base,
+            &author.name,
+            event.content.as_deref().unwrap_or_default(),
+            event.sticker_items.as_deref().unwrap_or_default(),
+        );
+
+        self.track(&context, &settings, &author.name, mentions)
+            .await;
+    }
+
+    async fn message_delete(
+        &self,
+        _context: Context,
+        _channel_id: ChannelId,
+        deleted_message_id: MessageId,
+        guild_id: Option<GuildId>,
+    ) {
+        if let Some(guild_id) = guild_id {
+            self.revert(guild_id, vec![deleted_message_id]).await;
+        }
+    }
+
+    async fn message_delete_bulk(
+        &self,
+        _context: Context,
+        _channel_id: ChannelId,
+        multiple_deleted_messages_ids: Vec<MessageId>,
+        guild_id: Option<GuildId>,
+    ) {
+        if let Some(guild_id) = guild_id {
+            self.revert(guild_id, multiple_deleted_messages_ids).await;
+        }
+    }
+
+    async fn reaction_add(&self, context: Context, reaction: Reaction) {
+        let (guild_id, user_id) = match (reaction.guild_id, reaction.user_id) {
+            (Some(guild_id), Some(user_id)) => (guild_id, user_id),
+            _ => return,
+        };
+
+        if user_id == context.cache.current_user().id {
+            return;
+        }
+
+        let settings = match self.settings.load(guild_id) {
+            Ok(settings) => settings,
+            Err(e) => {
+                tracing::error!(
+                    "An error occurred loading the settings of {}: {}",
+                    guild_id,
+                    e
+                );
+                return;
+            }
+        };
+
+        if !settings.sources.reactions.enabled {
+            return;
+        }
+
+        let emoji = match &reaction.emoji {
+            ReactionType::Custom {
+                id,
+                name: Some(name),
+                ..
+            } => Emoji::Custom { id: id.0, name },
+            ReactionType::Unicode(emoji) => Emoji::Unicode(emoji),
+            _ => return,
+        };
+        if !settings.sources.matches_emoji(&emoji) {
+            return;
+        }
+
+        let user = match reaction.user(&context).await {
+            Ok(user) => user,
+            Err(e) => {
+                tracing::error!("An error occurred fetching the user of a reaction: {}", e);
+                return;
+            }
+        };
+        let roles = reaction
+            .member
+            .as_ref()
+            .map_or(&[][..], |member| member.roles.as_slice());
+        if settings.ignore.ignores_user(&user, roles)
+            || !settings
+                .channels
+                .allows_channel(&context, reaction.channel_id)
+                .await
+        {
+            return;
+        }
+
+        tracing::info!(
+            "{} mentioned Rust by reacting with {}.",
+            user.name,
+            reaction.emoji
+        );
+
+        // Reactions carry no timestamp of their own, so the time they were received is used.
+        let mention = Mention {
+            guild_id,
+            channel_id: reaction.channel_id,
+            user_id,
+            message_id: reaction.message_id,
+            source: MentionSource::Reaction,
+            weight: settings.sources.reactions.weight,
+            sent_at: self.clock.now(),
+            rule: Some(reaction.emoji.to_string()),
+        };
+        self.track(&context, &settings, &user.name, vec![mention])
+            .await;
+    }
+
+    async fn interaction_create(&self, context: Context, interaction: Interaction) {
+        let result = match interaction {
+            Interaction::ApplicationCommand(command) => match command.data.name.as_str() {
+                "leaderboard" => {
+                    commands::leaderboard::run(
+                        &context,
+                        self.storage.as_ref(),
+                        &self.states,
+                        &command,
+                        &self.embed_style(command.guild_id),
+                        self.clock.now(),
+                    )
+                    .await
+                }
+                "crabe" => {
+                    commands::crabe::run(
+                        &context,
+                        self.storage.as_ref(),
+                        &self.states,
+                        &self.settings,
+                        &command,
+                        self.clock.now(),
+                    )
+                    .await
+                }
+                "since" => {
+                    commands::since::run(
+                        &context,
+                        &self.states,
+                        &command,
+                        &self.embed_style(command.guild_id),
+                        self.clock.now(),
+                    )
+                    .await
+                }
+                _ => Ok(()),
+            },
+            Interaction::MessageComponent(component) => {
+                match component.data.custom_id.parse::<PageRequest>() {
+                    Ok(request) => {
+                        commands::leaderboard::paginate(
+                            &context,
+                            self.storage.as_ref(),
+                            &self.states,
+                            &component,
+                            request,
+                            &self.embed_style(component.guild_id),
+                            self.clock.now(),
+                        )
+                        .await
+                    }
+                    Err(_) => Ok(()),
+                }
+            }
+            _ => Ok(()),
+        };
+
+        if let Err(e) = result {
+            tracing::error!("An error occurred responding to an interaction: {}", e);
+        }
+    }
+
+    async fn ready(&self, context: Context, data: Ready) {
+        tracing::info!("{} is connected and running.", data.user.name);
+
+        if let Err(e) = Command::set_global_application_commands(&context.http, |commands| {
+            commands
+                .create_application_command(|command| commands::leaderboard::register(command))
+                .create_application_command(|command| commands::crabe::register(command))
+                .create_application_command(|command| commands::since::register(command))
+        })
+        .await
+        {
+            tracing::error!(
+                "An error occurred registering the application commands: {}",
+                e
+            );
+        }
+
+        // The ready event is dispatched again after reconnecting, but the scheduler only needs to
+        // be started once.
+        if !self.scheduler_started.swap(true, Ordering::SeqCst) {
+            let storage = self.storage.clone();
+            let settings = self.settings.clone();
+            let states = self.states.clone();
+            Scheduler::new(storage.clone(), self.clock.clone())
+                .with_job(ReportJob::new(
+                    context.clone(),
+                    settings.clone(),
+                    states.clone(),
+                ))
+                .with_job(MilestoneJob::new(
+                    context.clone(),
+                    settings.clone(),
+                    states.clone(),
+                ))
+                .with_job(RetentionJob::new(context, settings, states))
+                .with_job(FlushJob::new(storage))
+                .with_tick_interval(self.tick_interval)
+                .spawn();
+        }
+    }
+}
@@ -0,0 +1,59 @@
+use serde::{Deserialize, Serialize};
+use serenity::model::{
+    channel::{Message, MessageType},
+    prelude::{RoleId, User, UserId},
+};
+
+/// Whose mentions of Rust aren't counted, such as bots posting crate updates.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct Ignore {
+    /// Ignores bot accounts.
+    pub bots: bool,
+    /// Ignores messages posted through webhooks, e.g. by GitHub or RSS integrations.
+    pub webhooks: bool,
+    /// Ignores messages generated by Discord, e.g. about members joining or pinned messages.
+    pub system_messages: bool,
+    /// The IDs of users, or webhooks, whose mentions are ignored.
+    pub users: Vec<UserId>,
+    /// The IDs of roles whose members' mentions are ignored.
+    pub roles: Vec<RoleId>,
+}
+
+impl Default for Ignore {
+    fn default() -> Self {
+        Self {
+            bots: true,
+            webhooks: true,
+            system_messages: true,
+            users: Vec::new(),
+            roles: Vec::new(),
+        }
+    }
+}
+
+impl Ignore {
+    /// Whether the mentions of a user with the given roles are ignored.
+    pub fn ignores_user(&self, user: &User, roles: &[RoleId]) -> bool {
+        (self.bots && user.bot)
+            || self.users.contains(&user.id)
+            || roles.iter().any(|role| self.roles.contains(role))
+    }
+
+    /// Whether the mentions in a message are ignored, judging by both the message and its author.
+    pub fn ignores_message(&self, msg: &Message, roles: &[RoleId]) -> bool {
+        // The author of a webhook message is the webhook itself, which is always flagged as a bot.
+        if msg.webhook_id.is_some() {
+            return self.webhooks || self.users.contains(&msg.author.id);
+        }
+
+        let system = !matches!(
+            msg.kind,
+            MessageType::Regular
+                | MessageType::InlineReply
+                | MessageType::ChatInputCommand
+                | MessageType::ContextMenuCommand
+        );
+        (self.system_messages && system) || self.ignores_user(&msg.author, roles)
+    }
+}
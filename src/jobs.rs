@@ -0,0 +1,224 @@
+use std::{collections::HashMap, sync::Arc};
+
+use chrono::{DateTime, Duration, Utc};
+use crabe_core::{humanize::humanize, scheduler::Job};
+use serenity::{client::Context, model::prelude::GuildId};
+use tokio::sync::Mutex;
+
+use crate::{report, settings::Settings, state::GuildStates, storage::Storage};
+
+/// Posts the reports of all guilds whose report schedule is due.
+pub struct ReportJob {
+    context: Context,
+    settings: Arc<Settings>,
+    states: Arc<GuildStates>,
+}
+
+impl ReportJob {
+    pub fn new(context: Context, settings: Arc<Settings>, states: Arc<GuildStates>) -> Self {
+        Self {
+            context,
+            settings,
+            states,
+        }
+    }
+}
+
+#[serenity::async_trait]
+impl Job for ReportJob {
+    fn name(&self) -> &'static str {
+        "reports"
+    }
+
+    fn next_run(&self, last_run: DateTime<Utc>) -> DateTime<Utc> {
+        last_run + Duration::minutes(1)
+    }
+
+    async fn run(&self, now: DateTime<Utc>) {
+        for guild_id in self.context.cache.guilds() {
+            report::run_if_due(&self.context, &self.settings, &self.states, guild_id, now).await;
+        }
+    }
+}
+
+/// Announces when the ongoing streak of a guild has beaten its record, without waiting for the
+/// next mention of Rust to end it.
+pub struct MilestoneJob {
+    context: Context,
+    settings: Arc<Settings>,
+    states: Arc<GuildStates>,
+    /// The last mention of each guild whose streak has already been announced.
+    announced: Mutex<HashMap<GuildId, DateTime<Utc>>>,
+}
+
+impl MilestoneJob {
+    pub fn new(context: Context, settings: Arc<Settings>, states: Arc<GuildStates>) -> Self {
+        Self {
+            context,
+            settings,
+            states,
+            announced: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+#[serenity::async_trait]
+impl Job for MilestoneJob {
+    fn name(&self) -> &'static str {
+        "milestones"
+    }
+
+    fn next_run(&self, last_run: DateTime<Utc>) -> DateTime<Utc> {
+        last_run + Duration::minutes(1)
+    }
+
+    async fn run(&self, now: DateTime<Utc>) {
+        for guild_id in self.context.cache.guilds() {
+            let record = self.states.record(guild_id, None).await;
+            let (last_mention, (current, previous)) =
+                match (record.last_mention, record.ongoing_streak_beating(now)) {
+                    (Some(last_mention), Some(streaks)) => (last_mention, streaks),
+                    _ => continue,
+                };
+
+            {
+                let mut announced = self.announced.lock().await;
+                if announced.get(&guild_id) == Some(&last_mention) {
+                    continue;
+                }
+                announced.insert(guild_id, last_mention);
+            }
+
+            let settings = match self.settings.load(guild_id) {
+                Ok(settings) => settings,
+                Err(e) => {
+                    tracing::error!(
+                        "An error occurred loading the settings of {}: {}",
+                        guild_id,
+                        e
+                    );
+                    continue;
+                }
+            };
+            if !settings.announce_milestones {
+                continue;
+            }
+
+            let channel_id = match report::report_channel(&self.context, guild_id, &settings) {
+                Some(channel_id) => channel_id,
+                None => continue,
+            };
+
+            if let Err(e) = channel_id
+                .send_message(&self.context.http, |m| {
+                    m.embed(|e| {
+                        e.title("🦀 A new record is in the making! 🦀")
+                            .description(match previous {
+                                Some(previous) => format!(
+                                    "Nobody has mentioned Rust for {}, beating the previous record of {}. Keep going!",
+                                    humanize(current),
+                                    humanize(previous)
+                                ),
+                                None => format!(
+                                    "Nobody has mentioned Rust for {}, the longest since the record was cleared. Keep going!",
+                                    humanize(current)
+                                ),
+                            })
+                            .color(settings.embed.color)
+                            .footer(|f| f.text(&settings.embed.footer))
+                    })
+                })
+                .await
+            {
+                tracing::error!("An error occurred sending a milestone message: {}", e);
+            }
+        }
+    }
+}
+
+/// Compacts the mentions older than the retention period configured by each guild into its
+/// baseline, so they no longer take up space but still count.
+pub struct RetentionJob {
+    context: Context,
+    settings: Arc<Settings>,
+    states: Arc<GuildStates>,
+}
+
+impl RetentionJob {
+    pub fn new(context: Context, settings: Arc<Settings>, states: Arc<GuildStates>) -> Self {
+        Self {
+            context,
+            settings,
+            states,
+        }
+    }
+}
+
+#[serenity::async_trait]
+impl Job for RetentionJob {
+    fn name(&self) -> &'static str {
+        "retention"
+    }
+
+    fn next_run(&self, last_run: DateTime<Utc>) -> DateTime<Utc> {
+        last_run + Duration::days(1)
+    }
+
+    async fn run(&self, now: DateTime<Utc>) {
+        for guild_id in self.context.cache.guilds() {
+            let retention_days = match self.settings.load(guild_id) {
+                Ok(settings) => match settings.mention_retention_days {
+                    Some(retention_days) => retention_days,
+                    None => continue,
+                },
+                Err(e) => {
+                    tracing::error!(
+                        "An error occurred loading the settings of {}: {}",
+                        guild_id,
+                        e
+                    );
+                    continue;
+                }
+            };
+
+            let cutoff = now - Duration::days(retention_days.into());
+            let compacted = self.states.compact(guild_id, cutoff).await;
+            if compacted > 0 {
+                tracing::info!(
+                    "Compacted {} mentions of {} before {}.",
+                    compacted,
+                    guild_id,
+                    cutoff
+                );
+            }
+        }
+    }
+}
+
+/// Flushes the pending writes of the storage to disk.
+pub struct FlushJob {
+    storage: Arc<dyn Storage>,
+}
+
+impl FlushJob {
+    pub fn new(storage: Arc<dyn Storage>) -> Self {
+        Self { storage }
+    }
+}
+
+#[serenity::async_trait]
+impl Job for FlushJob {
+    fn name(&self) -> &'static str {
+        "flush"
+    }
+
+    fn next_run(&self, last_run: DateTime<Utc>) -> DateTime<Utc> {
+        last_run + Duration::minutes(5)
+    }
+
+    async fn run(&self, _: DateTime<Utc>) {
+        if let Err(e) = self.storage.flush() {
+            tracing::error!("An error occurred flushing the storage: {}", e);
+        }
+    }
+}
@@ -0,0 +1,158 @@
+//! The Discord side of the bot, which tracks the mentions of Rust on its servers through the
+//! domain of `crabe-core`. The binary only reads its configuration from the environment, so the
+//! bot can be started against a fake Discord in tests as well.
+
+pub mod audit;
+pub mod channels;
+pub mod commands;
+pub mod config;
+mod handler;
+mod ignore;
+mod jobs;
+mod report;
+pub mod settings;
+pub mod state;
+pub mod storage;
+
+use std::{fmt, io, path::PathBuf, sync::Arc, time::Duration};
+
+use config::{Config, ConfigError};
+use crabe_core::{
+    clock::{Clock, SystemClock},
+    scheduler::TICK_INTERVAL,
+};
+use handler::Handler;
+use serenity::{client::ClientBuilder, http::HttpBuilder, prelude::GatewayIntents, Client};
+use settings::Settings;
+use state::GuildStates;
+use storage::{Storage, StorageError};
+
+#[derive(Debug)]
+pub enum BotError {
+    Storage(StorageError),
+    Config(ConfigError),
+    Io(io::Error),
+    Discord(serenity::Error),
+}
+
+impl fmt::Display for BotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BotError::Storage(e) => write!(f, "Storage error: {}", e),
+            BotError::Config(e) => write!(f, "Config error: {}", e),
+            BotError::Io(e) => write!(f, "I/O error: {}", e),
+            BotError::Discord(e) => write!(f, "Discord error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for BotError {}
+
+impl From<StorageError> for BotError {
+    fn from(e: StorageError) -> Self {
+        BotError::Storage(e)
+    }
+}
+
+impl From<ConfigError> for BotError {
+    fn from(e: ConfigError) -> Self {
+        BotError::Config(e)
+    }
+}
+
+impl From<io::Error> for BotError {
+    fn from(e: io::Error) -> Self {
+        BotError::Io(e)
+    }
+}
+
+impl From<serenity::Error> for BotError {
+    fn from(e: serenity::Error) -> Self {
+        BotError::Discord(e)
+    }
+}
+
+/// Builds the client running the bot, which by default talks to Discord and uses the system
+/// clock.
+pub struct Bot {
+    storage: Arc<dyn Storage>,
+    clock: Arc<dyn Clock>,
+    config_path: Option<PathBuf>,
+    api_url: Option<String>,
+    tick_interval: Duration,
+}
+
+impl Bot {
+    pub fn new(storage: Arc<dyn Storage>) -> Self {
+        Self {
+            storage,
+            clock: Arc::new(SystemClock),
+            config_path: None,
+            api_url: None,
+            tick_interval: TICK_INTERVAL,
+        }
+    }
+
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Reads the default settings of all guilds and overrides for single ones from a TOML file,
+    /// reloading it whenever it changes or the bot receives `SIGHUP`.
+    pub fn with_config_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.config_path = Some(path.into());
+        self
+    }
+
+    /// Sends the REST API requests to another server than Discord, such as a proxy.
+    ///
+    /// The URL of the gateway is requested from that server as well, so a fake Discord can take
+    /// the place of both. Serenity only sends its requests there with its own rate limiter
+    /// disabled, so respecting the rate limits is up to that server.
+    pub fn with_api_url(mut self, api_url: impl Into<String>) -> Self {
+        self.api_url = Some(api_url.into());
+        self
+    }
+
+    /// Checks whether any scheduled jobs are due more or less often than by default.
+    pub fn with_tick_interval(mut self, tick_interval: Duration) -> Self {
+        self.tick_interval = tick_interval;
+        self
+    }
+
+    /// Loads the config file and the state of the guilds and creates the client, which still has
+    /// to be started.
+    pub async fn client(self, token: &str) -> Result<Client, BotError> {
+        let config = match &self.config_path {
+            Some(path) => Config::load(path)?,
+            None => Config::default(),
+        };
+        let settings = Arc::new(Settings::new(self.storage.clone(), config));
+        if let Some(path) = self.config_path {
+            config::watch(path, settings.clone())?;
+        }
+
+        let states = Arc::new(GuildStates::load(self.storage.clone(), self.clock.clone())?);
+
+        let mut http = HttpBuilder::new(token);
+        if let Some(api_url) = &self.api_url {
+            http = http.proxy(api_url.as_str())?.ratelimiter_disabled(true);
+        }
+
+        let intents = GatewayIntents::GUILD_MESSAGES
+            | GatewayIntents::GUILD_MESSAGE_REACTIONS
+            | GatewayIntents::MESSAGE_CONTENT
+            | GatewayIntents::GUILDS;
+        let client = ClientBuilder::new_with_http(http.build(), intents)
+            .event_handler(Handler::new(
+                self.storage,
+                settings,
+                self.clock,
+                self.tick_interval,
+                states,
+            ))
+            .await?;
+        Ok(client)
+    }
+}
@@ -0,0 +1,110 @@
+use chrono::{DateTime, Utc};
+use crabe_core::leaderboard;
+use serenity::{
+    client::Context,
+    model::prelude::{ChannelId, GuildId},
+    utils::MessageBuilder,
+};
+
+use crate::{
+    settings::{GuildSettings, Settings},
+    state::GuildStates,
+};
+
+/// Posts the report of a guild if its schedule is due at `now`.
+pub async fn run_if_due(
+    context: &Context,
+    settings: &Settings,
+    states: &GuildStates,
+    guild_id: GuildId,
+    now: DateTime<Utc>,
+) {
+    let settings = match settings.load(guild_id) {
+        Ok(settings) => settings,
+        Err(e) => {
+            tracing::error!(
+                "An error occurred loading the settings of {}: {}",
+                guild_id,
+                e
+            );
+            return;
+        }
+    };
+    let schedule = match settings.schedule() {
+        Ok(schedule) => schedule,
+        Err(reason) => {
+            tracing::warn!("The report schedule of {} is invalid: {}", guild_id, reason);
+            return;
+        }
+    };
+
+    let is_due = states.claim_report(guild_id, schedule, now).await;
+    if !is_due {
+        return;
+    }
+
+    match report_channel(context, guild_id, &settings) {
+        Some(channel_id) => send_report(context, &settings, states, guild_id, channel_id).await,
+        None => tracing::warn!("There is no channel to post the report of {} to.", guild_id),
+    }
+}
+
+/// The channel the reports and announcements of a guild are posted to.
+pub fn report_channel(
+    context: &Context,
+    guild_id: GuildId,
+    settings: &GuildSettings,
+) -> Option<ChannelId> {
+    settings.report_channel.or_else(|| {
+        context
+            .cache
+            .guild_channels(guild_id)?
+            .iter()
+            .find(|c| c.name == settings.report_channel_name)
+            .map(|c| c.id)
+    })
+}
+
+pub async fn send_report(
+    context: &Context,
+    settings: &GuildSettings,
+    states: &GuildStates,
+    guild_id: GuildId,
+    channel_id: ChannelId,
+) {
+    let mut message_builder = MessageBuilder::new();
+    message_builder.push("👋 Hello everyone!\n\nIt's time to check who has mentioned Rust the most on the server. Here are the results:\n\n");
+
+    let top_mentions = leaderboard::rank(states.mention_counts(guild_id).await)
+        .into_iter()
+        .take(5)
+        .collect::<Vec<_>>();
+
+    for (user_id, count) in top_mentions {
+        if let Ok(user) = user_id.to_user(context).await {
+            let name = user.nick_in(context, guild_id).await.unwrap_or(user.name);
+            message_builder.push(format!(
+                "**{}**: {} mention{}\n",
+                name,
+                count,
+                if count == 1 { "" } else { "s" }
+            ));
+        }
+    }
+
+    message_builder.push("\nCongratulations to the winners! 🎉");
+
+    if let Err(e) = channel_id
+        .send_message(&context.http, |m| {
+            m.embed(|e| {
+                e.title("🦀 Rust Report 🦀")
+                    .description(message_builder.build())
+                    .color(settings.embed.color)
+                    .footer(|f| f.text(&settings.embed.footer))
+            })
+        })
+        .await
+    {
+        tracing::error!("An error occurred sending a report message: {}", e);
+    }
+}
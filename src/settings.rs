@@ -0,0 +1,397 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{Arc, Mutex, RwLock},
+};
+
+use crabe_core::{
+    detection::KeywordRules, markdown::Regions, normalize::Normalization, schedule::ReportSchedule,
+    sources::Sources,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use serenity::model::prelude::{ChannelId, GuildId, RoleId};
+
+use crate::{
+    channels::Channels,
+    config::Config,
+    ignore::Ignore,
+    storage::{Storage, StorageError},
+};
+
+/// The longest deletion grace window a guild can configure.
+pub const MAX_DELETION_GRACE_SECONDS: u64 = 600;
+
+/// Which records are announced when a mention beats them.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AnnounceLevel {
+    #[default]
+    Server,
+    Channel,
+    Both,
+    Off,
+}
+
+/// How the embeds posted by the bot look.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct EmbedStyle {
+    /// The color of the stripe along the embeds, as an RGB value such as `0xdea584`.
+    pub color: u32,
+    /// The text shown at the bottom of the embeds.
+    pub footer: String,
+}
+
+impl Default for EmbedStyle {
+    fn default() -> Self {
+        Self {
+            color: 0xdea584,
+            footer: "Made with  ❤️  and  🦀  by Near".to_string(),
+        }
+    }
+}
+
+/// The settings each guild can adjust to its needs.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct GuildSettings {
+    /// The channel the reports are posted to, falling back to the channel named
+    /// `report_channel_name`.
+    pub report_channel: Option<ChannelId>,
+    /// The name of the channel the reports are posted to unless `report_channel` is set.
+    pub report_channel_name: String,
+    /// A cron expression such as `0 9 * * Mon` describing when reports are posted.
+    pub report_schedule: String,
+    /// The IANA time zone the report schedule is evaluated in, e.g. `Europe/Berlin`.
+    pub timezone: String,
+    /// Whether to announce when the ongoing streak beats the record.
+    pub announce_milestones: bool,
+    /// Which records are announced when a mention ends a streak longer than them.
+    pub announce_records: AnnounceLevel,
+    /// The number of days mentions are kept for the leaderboards, or forever if unset.
+    pub mention_retention_days: Option<u32>,
+    /// The keywords counted as a mention of Rust.
+    pub keywords: KeywordRules,
+    /// The parts of a message searched for the keywords, depending on their markdown.
+    pub regions: Regions,
+    /// The normalization applied to a message before searching it for the keywords.
+    pub normalization: Normalization,
+    /// Which sources mentions are counted from and how much each of them weighs.
+    pub sources: Sources,
+    /// The number of seconds after which deleting a message no longer reverts its mentions.
+    pub deletion_grace_seconds: u64,
+    /// Whose mentions aren't counted.
+    pub ignore: Ignore,
+    /// The channels, categories and threads mentions are counted in.
+    pub channels: Channels,
+    /// How the embeds posted by the bot look.
+    pub embed: EmbedStyle,
+    /// The role whose members may configure the bot besides those with the Manage Server
+    /// permission.
+    pub admin_role: Option<RoleId>,
+    /// The channel the audit log is mirrored to, if any.
+    pub mod_log_channel: Option<ChannelId>,
+}
+
+impl Default for GuildSettings {
+    fn default() -> Self {
+        Self {
+            report_channel: None,
+            report_channel_name: "random".to_string(),
+            report_schedule: "0 9 * * Mon".to_string(),
+            timezone: "UTC".to_string(),
+            announce_milestones: true,
+            announce_records: AnnounceLevel::default(),
+            mention_retention_days: None,
+            keywords: KeywordRules::default(),
+            regions: Regions::default(),
+            normalization: Normalization::default(),
+            sources: Sources::default(),
+            deletion_grace_seconds: 30,
+            ignore: Ignore::default(),
+            channels: Channels::default(),
+            embed: EmbedStyle::default(),
+            admin_role: None,
+            mod_log_channel: None,
+        }
+    }
+}
+
+impl GuildSettings {
+    /// Checks that the settings can be used, describing the first problem otherwise.
+    pub fn validate(&self) -> Result<(), String> {
+        self.schedule()?;
+        if self.deletion_grace_seconds > MAX_DELETION_GRACE_SECONDS {
+            return Err(format!(
+                "The deletion grace window can't be longer than {} seconds.",
+                MAX_DELETION_GRACE_SECONDS
+            ));
+        }
+        self.keywords.validate()
+    }
+
+    /// Resolves the settings given by layers of overrides, applied from the first to the last on
+    /// top of the defaults, as long as they are valid.
+    pub fn layered(layers: &[&Overrides]) -> Result<Self, String> {
+        let mut settings = defaults();
+        for layer in layers {
+            check_keys(&settings, layer, "")?;
+            merge(&mut settings, layer);
+        }
+
+        let settings =
+            serde_json::from_value::<Self>(Value::Object(settings)).map_err(|e| e.to_string())?;
+        settings.validate()?;
+        Ok(settings)
+    }
+
+    pub fn schedule(&self) -> Result<ReportSchedule, String> {
+        ReportSchedule::new(&self.report_schedule, &self.timezone)
+    }
+}
+
+/// Settings overriding others, holding only the values that differ from them.
+///
+/// Nested objects are merged into the settings they override, while any other value replaces the
+/// one it overrides as a whole.
+pub type Overrides = Map<String, Value>;
+
+/// The compiled defaults as a JSON object, which the overrides are merged into.
+fn defaults() -> Map<String, Value> {
+    match serde_json::to_value(GuildSettings::default()) {
+        Ok(Value::Object(defaults)) => defaults,
+        _ => unreachable!("The settings are always serialized as an object."),
+    }
+}
+
+/// Merges overrides into the settings, or other overrides.
+fn merge(target: &mut Map<String, Value>, overrides: &Overrides) {
+    for (key, value) in overrides {
+        match (target.get_mut(key), value) {
+            (Some(Value::Object(target)), Value::Object(overrides)) => merge(target, overrides),
+            _ => {
+                target.insert(key.clone(), value.clone());
+            }
+        }
+    }
+}
+
+/// Makes sure that overrides only contain settings that exist, so typos don't go unnoticed.
+fn check_keys(
+    settings: &Map<String, Value>,
+    overrides: &Overrides,
+    prefix: &str,
+) -> Result<(), String> {
+    for (key, value) in overrides {
+        let path = format!("{}{}", prefix, key);
+        match (settings.get(key), value) {
+            (None, _) => return Err(format!("There is no setting called `{}`.", path)),
+            (Some(Value::Object(settings)), Value::Object(overrides)) => {
+                check_keys(settings, overrides, &format!("{}.", path))?
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Looks up a setting by its dot-separated path, which is null if it doesn't exist.
+fn lookup(settings: &Value, key: &str) -> Value {
+    key.split('.')
+        .try_fold(settings, |value, part| value.get(part))
+        .cloned()
+        .unwrap_or_default()
+}
+
+/// A setting that was changed, with its value before and after the change.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SettingChange {
+    pub key: String,
+    pub old_value: Value,
+    pub new_value: Value,
+}
+
+#[derive(Debug)]
+pub enum SettingsError {
+    Storage(StorageError),
+    /// The settings would be invalid, described in a way that can be shown to the user.
+    Invalid(String),
+}
+
+impl fmt::Display for SettingsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SettingsError::Storage(e) => write!(f, "{}", e),
+            SettingsError::Invalid(reason) => write!(f, "Invalid settings: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for SettingsError {}
+
+impl From<StorageError> for SettingsError {
+    fn from(e: StorageError) -> Self {
+        SettingsError::Storage(e)
+    }
+}
+
+/// Resolves the settings of each guild.
+///
+/// The settings are layered, each overriding the ones before: the compiled defaults, the defaults
+/// of the config file, its overrides for the guild and finally the settings the guild changed
+/// through the commands. Only the latter are stored, so changing the config file affects every
+/// setting a guild didn't change itself.
+///
+/// The resolved settings are cached, so they are only read, merged and validated again once a
+/// guild changed them or the config file was reloaded.
+pub struct Settings {
+    storage: Arc<dyn Storage>,
+    config: RwLock<Arc<Config>>,
+    resolved: Mutex<HashMap<GuildId, GuildSettings>>,
+}
+
+impl Settings {
+    pub fn new(storage: Arc<dyn Storage>, config: Config) -> Self {
+        Self {
+            storage,
+            config: RwLock::new(Arc::new(config)),
+            resolved: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Replaces the config file, taking effect the next time the settings of a guild are loaded.
+    pub fn reload(&self, config: Config) {
+        *self.config.write().expect("The config lock was poisoned.") = Arc::new(config);
+        self.resolved().clear();
+    }
+
+    pub fn load(&self, guild_id: GuildId) -> Result<GuildSettings, SettingsError> {
+        // The cache stays locked while resolving, so a change or reload happening meanwhile
+        // can't be overwritten by the settings from before it.
+        self.resolve(&mut self.resolved(), guild_id)
+    }
+
+    /// Looks up the settings of a guild in the locked cache, resolving them on a miss.
+    fn resolve(
+        &self,
+        resolved: &mut HashMap<GuildId, GuildSettings>,
+        guild_id: GuildId,
+    ) -> Result<GuildSettings, SettingsError> {
+        if let Some(settings) = resolved.get(&guild_id) {
+            return Ok(settings.clone());
+        }
+
+        let config = self.config();
+        let overrides = self.storage.load_setting_overrides(guild_id)?;
+        let mut layers = config.layers(guild_id);
+        layers.push(&overrides);
+
+        let settings = GuildSettings::layered(&layers).map_err(SettingsError::Invalid)?;
+        resolved.insert(guild_id, settings.clone());
+        Ok(settings)
+    }
+
+    /// Changes a single setting of a guild, addressed by its dot-separated path such as
+    /// `report_channel`, returning the change unless the setting already had that value.
+    ///
+    /// The value is read as JSON and falls back to a plain string, so `123` sets a number while
+    /// `Europe/Berlin` sets a string. The setting is only changed if the settings remain valid.
+    pub fn set(
+        &self,
+        guild_id: GuildId,
+        key: &str,
+        value: &str,
+    ) -> Result<Vec<SettingChange>, SettingsError> {
+        let parsed =
+            serde_json::from_str(value).unwrap_or_else(|_| Value::String(value.to_string()));
+        self.change(guild_id, &[(key, parsed)], |reason| {
+            format!("`{}` is not a valid value for `{}`: {}", value, key, reason)
+        })
+    }
+
+    /// Changes a single setting of a guild to a JSON value, like [`Settings::set`].
+    pub fn set_value(
+        &self,
+        guild_id: GuildId,
+        key: &str,
+        value: Value,
+    ) -> Result<Vec<SettingChange>, SettingsError> {
+        let shown = value.to_string();
+        self.change(guild_id, &[(key, value)], |reason| {
+            format!("`{}` is not a valid value for `{}`: {}", shown, key, reason)
+        })
+    }
+
+    /// Changes several settings of a guild at once, so either all or none of them are changed.
+    pub fn set_values(
+        &self,
+        guild_id: GuildId,
+        values: &[(&str, Value)],
+    ) -> Result<Vec<SettingChange>, SettingsError> {
+        self.change(guild_id, values, |reason| reason)
+    }
+
+    /// Stores changes of single settings as long as the settings remain valid, describing why
+    /// they don't otherwise.
+    fn change(
+        &self,
+        guild_id: GuildId,
+        values: &[(&str, Value)],
+        describe: impl FnOnce(String) -> String,
+    ) -> Result<Vec<SettingChange>, SettingsError> {
+        // The cache stays locked from reading the stored overrides until the changed settings are
+        // cached, so concurrent changes can't lose each other's updates, and a reload meanwhile
+        // only clears the cache once the settings built from the previous config are in it.
+        let mut resolved = self.resolved();
+        let before = self.resolve(&mut resolved, guild_id).ok();
+
+        let mut overrides = self.storage.load_setting_overrides(guild_id)?;
+        for (key, value) in values {
+            let change = key.rsplit('.').fold(value.clone(), |value, part| {
+                Value::Object(Map::from_iter([(part.to_string(), value)]))
+            });
+            let change = match change {
+                Value::Object(change) => change,
+                _ => unreachable!("The change is wrapped in at least one object."),
+            };
+            check_keys(&defaults(), &change, "").map_err(SettingsError::Invalid)?;
+            merge(&mut overrides, &change);
+        }
+
+        let config = self.config();
+        let mut layers = config.layers(guild_id);
+        layers.push(&overrides);
+        let after = GuildSettings::layered(&layers)
+            .map_err(|reason| SettingsError::Invalid(describe(reason)))?;
+
+        self.storage.save_setting_overrides(guild_id, &overrides)?;
+        resolved.insert(guild_id, after.clone());
+        drop(resolved);
+
+        let before = serde_json::to_value(before).unwrap_or_default();
+        let after = serde_json::to_value(after).unwrap_or_default();
+        Ok(values
+            .iter()
+            .map(|(key, _)| SettingChange {
+                key: key.to_string(),
+                old_value: lookup(&before, key),
+                new_value: lookup(&after, key),
+            })
+            .filter(|change| change.old_value != change.new_value)
+            .collect())
+    }
+
+    fn resolved(&self) -> std::sync::MutexGuard<'_, HashMap<GuildId, GuildSettings>> {
+        self.resolved
+            .lock()
+            .expect("The resolved settings mutex was poisoned.")
+    }
+
+    fn config(&self) -> Arc<Config> {
+        self.config
+            .read()
+            .expect("The config lock was poisoned.")
+            .clone()
+    }
+}
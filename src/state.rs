@@ -0,0 +1,798 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+pub use crabe_core::tracker::Tracked;
+use crabe_core::{
+    clock::Clock,
+    record::Record,
+    schedule::ReportSchedule,
+    sources::MentionSource,
+    tracker::{self, Tally},
+};
+use serenity::model::prelude::{ChannelId, GuildId, MessageId, UserId};
+use tokio::sync::{mpsc, oneshot};
+
+use crate::{
+    settings::MAX_DELETION_GRACE_SECONDS,
+    storage::{self, Storage},
+};
+
+/// How many requests can queue up for a single guild before senders have to wait.
+const QUEUE_CAPACITY: usize = 64;
+
+/// A single mention of Rust, either in a message or as a reaction to one.
+#[derive(Clone)]
+pub struct Mention {
+    pub guild_id: GuildId,
+    pub channel_id: ChannelId,
+    pub user_id: UserId,
+    pub message_id: MessageId,
+    pub source: MentionSource,
+    /// How many mentions this one counts as.
+    pub weight: usize,
+    pub sent_at: DateTime<Utc>,
+    /// What matched: the pattern of the keyword rule for text, or the emoji or sticker otherwise.
+    /// Unknown for mentions saved before it was recorded.
+    pub rule: Option<String>,
+}
+
+impl Mention {
+    /// The part of the mention the counts and records are derived from.
+    pub fn tally(&self) -> Tally<ChannelId, UserId> {
+        Tally {
+            channel_id: self.channel_id,
+            user_id: self.user_id,
+            weight: self.weight,
+            at: self.sent_at,
+        }
+    }
+}
+
+/// A correction an admin made to the counts or records of a guild, kept in the log so it still
+/// applies when they are rebuilt.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Correction {
+    pub guild_id: GuildId,
+    pub kind: CorrectionKind,
+    pub at: DateTime<Utc>,
+}
+
+pub type CorrectionKind = tracker::CorrectionKind<ChannelId, UserId>;
+
+/// Everything the bot keeps track of for a single guild.
+pub type GuildState = tracker::GuildState<ChannelId, UserId>;
+
+/// The mentions taken back by disqualifying a message.
+pub struct Disqualified {
+    pub channel_id: ChannelId,
+    /// How many mentions were taken back from each user.
+    pub weights: HashMap<UserId, usize>,
+}
+
+/// The mentions of a message, kept for a while so they can be reverted if the message is deleted
+/// shortly after.
+struct TrackedMentions {
+    user_id: UserId,
+    sources: Vec<MentionSource>,
+    sent_at: DateTime<Utc>,
+}
+
+enum Request {
+    Track {
+        mentions: Vec<Mention>,
+        revertible: bool,
+        reply: oneshot::Sender<Option<Tracked>>,
+    },
+    Revert {
+        message_ids: Vec<MessageId>,
+        cutoff: DateTime<Utc>,
+        reply: oneshot::Sender<usize>,
+    },
+    Record {
+        channel_id: Option<ChannelId>,
+        reply: oneshot::Sender<Record>,
+    },
+    MentionCounts {
+        reply: oneshot::Sender<HashMap<UserId, usize>>,
+    },
+    SetMentionCount {
+        user_id: UserId,
+        count: usize,
+        reply: oneshot::Sender<usize>,
+    },
+    SetRecord {
+        channel_id: Option<ChannelId>,
+        duration: Option<Duration>,
+        reply: oneshot::Sender<Option<Duration>>,
+    },
+    Disqualify {
+        message_id: MessageId,
+        reply: oneshot::Sender<Option<Disqualified>>,
+    },
+    Rebuild {
+        rule: Option<String>,
+        reply: oneshot::Sender<Option<usize>>,
+    },
+    Compact {
+        before: DateTime<Utc>,
+        reply: oneshot::Sender<usize>,
+    },
+    ClaimReport {
+        schedule: Box<ReportSchedule>,
+        now: DateTime<Utc>,
+        reply: oneshot::Sender<bool>,
+    },
+}
+
+/// The state of all guilds, each owned by a task processing the requests for it one at a time.
+///
+/// Every request is handled in full before the next one of the same guild is looked at, so e.g.
+/// two mentions arriving at once can't both beat the record or overwrite each other's changes.
+/// The task of a guild is started the first time it is needed.
+pub struct GuildStates {
+    storage: Arc<dyn Storage>,
+    clock: Arc<dyn Clock>,
+    senders: Mutex<HashMap<GuildId, mpsc::Sender<Request>>>,
+    /// The states loaded on startup whose task hasn't been started yet.
+    loaded: Mutex<HashMap<GuildId, GuildState>>,
+}
+
+impl GuildStates {
+    /// Loads the state of every guild from the storage.
+    pub fn load(storage: Arc<dyn Storage>, clock: Arc<dyn Clock>) -> storage::Result<Self> {
+        let states = load_states(&*storage)?;
+        Ok(Self {
+            storage,
+            clock,
+            senders: Mutex::new(HashMap::new()),
+            loaded: Mutex::new(states),
+        })
+    }
+
+    /// Saves the new mentions of a single user found in a single event, counts them and ends the
+    /// ongoing streak, returning `None` if all of them were saved before.
+    ///
+    /// Revertible mentions are taken back by [`GuildStates::revert`] if their message is deleted
+    /// shortly after.
+    pub async fn track(&self, mentions: Vec<Mention>, revertible: bool) -> Option<Tracked> {
+        let guild_id = mentions.first()?.guild_id;
+        self.request(guild_id, |reply| Request::Track {
+            mentions,
+            revertible,
+            reply,
+        })
+        .await
+    }
+
+    /// Takes back the mentions of deleted messages if they were made after `cutoff`, returning
+    /// how many of the messages had any.
+    ///
+    /// The counts and records are rebuilt once for all messages, e.g. of a bulk deletion.
+    pub async fn revert(
+        &self,
+        guild_id: GuildId,
+        message_ids: Vec<MessageId>,
+        cutoff: DateTime<Utc>,
+    ) -> usize {
+        self.request(guild_id, |reply| Request::Revert {
+            message_ids,
+            cutoff,
+            reply,
+        })
+        .await
+    }
+
+    /// The record of a channel, or of the whole guild if no channel is given.
+    pub async fn record(&self, guild_id: GuildId, channel_id: Option<ChannelId>) -> Record {
+        self.request(guild_id, |reply| Request::Record { channel_id, reply })
+            .await
+    }
+
+    /// How many times each user of a guild has mentioned Rust.
+    pub async fn mention_counts(&self, guild_id: GuildId) -> HashMap<UserId, usize> {
+        self.request(guild_id, |reply| Request::MentionCounts { reply })
+            .await
+    }
+
+    /// Changes how many times a user of a guild has mentioned Rust, returning the previous count.
+    pub async fn set_mention_count(
+        &self,
+        guild_id: GuildId,
+        user_id: UserId,
+        count: usize,
+    ) -> usize {
+        self.request(guild_id, |reply| Request::SetMentionCount {
+            user_id,
+            count,
+            reply,
+        })
+        .await
+    }
+
+    /// Changes the record of a channel, or of the whole guild if no channel is given, keeping the
+    /// ongoing streak, or clears it without a duration. Returns the previous record.
+    pub async fn set_record(
+        &self,
+        guild_id: GuildId,
+        channel_id: Option<ChannelId>,
+        duration: Option<Duration>,
+    ) -> Option<Duration> {
+        self.request(guild_id, |reply| Request::SetRecord {
+            channel_id,
+            duration,
+            reply,
+        })
+        .await
+    }
+
+    /// Takes back all mentions of a message as if it never mentioned Rust, returning `None` if it
+    /// didn't count.
+    ///
+    /// Unlike [`GuildStates::revert`], this works at any time, as long as the mentions weren't
+    /// compacted yet.
+    pub async fn disqualify(
+        &self,
+        guild_id: GuildId,
+        message_id: MessageId,
+    ) -> Option<Disqualified> {
+        self.request(guild_id, |reply| Request::Disqualify { message_id, reply })
+            .await
+    }
+
+    /// Rebuilds the counts and records of a guild from its log, after disqualifying every mention
+    /// written in text that matched `rule` if one is given.
+    ///
+    /// Returns how many mentions were disqualified, or `None` if the log couldn't be read.
+    pub async fn rebuild(&self, guild_id: GuildId, rule: Option<String>) -> Option<usize> {
+        self.request(guild_id, |reply| Request::Rebuild { rule, reply })
+            .await
+    }
+
+    /// Folds the mentions and corrections of a guild before `before` into its baseline and
+    /// deletes them from the log, returning how many mentions there were.
+    pub async fn compact(&self, guild_id: GuildId, before: DateTime<Utc>) -> usize {
+        self.request(guild_id, |reply| Request::Compact { before, reply })
+            .await
+    }
+
+    /// Checks whether a report of a guild is due at `now`, recording that it was posted if so.
+    ///
+    /// A guild that was never reported on starts its schedule without a report being due.
+    pub async fn claim_report(
+        &self,
+        guild_id: GuildId,
+        schedule: ReportSchedule,
+        now: DateTime<Utc>,
+    ) -> bool {
+        self.request(guild_id, |reply| Request::ClaimReport {
+            schedule: Box::new(schedule),
+            now,
+            reply,
+        })
+        .await
+    }
+
+    /// Sends a request to the task of a guild and waits for its reply.
+    ///
+    /// A task that stopped, e.g. because it panicked, is restarted from the state in the storage.
+    /// The reply defaults if it stopped while handling the request.
+    async fn request<T: Default>(
+        &self,
+        guild_id: GuildId,
+        request: impl FnOnce(oneshot::Sender<T>) -> Request,
+    ) -> T {
+        let (reply, response) = oneshot::channel();
+        let mut request = request(reply);
+        loop {
+            let sender = self.sender(guild_id);
+            match sender.send(request).await {
+                Ok(()) => break,
+                Err(mpsc::error::SendError(unsent)) => {
+                    tracing::error!("The state task of {} has stopped, restarting it.", guild_id);
+                    if !self.restart(guild_id, &sender) {
+                        return T::default();
+                    }
+                    request = unsent;
+                }
+            }
+        }
+
+        match response.await {
+            Ok(response) => response,
+            Err(_) => {
+                tracing::error!(
+                    "The state task of {} stopped handling a request, restarting it.",
+                    guild_id
+                );
+                self.restart(guild_id, &self.sender(guild_id));
+                T::default()
+            }
+        }
+    }
+
+    /// Reloads the state of a guild whose task stopped, so the next request starts a new task
+    /// with it. Returns `false` if it couldn't be loaded, keeping the stopped task in place so
+    /// the next request tries again.
+    fn restart(&self, guild_id: GuildId, stopped: &mpsc::Sender<Request>) -> bool {
+        let mut senders = self
+            .senders
+            .lock()
+            .expect("The state senders mutex was poisoned.");
+        match senders.get(&guild_id) {
+            Some(sender) if sender.same_channel(stopped) && sender.is_closed() => {}
+            _ => return true,
+        }
+
+        let state = match load_states(&*self.storage) {
+            Ok(mut states) => states.remove(&guild_id).unwrap_or_default(),
+            Err(e) => {
+                tracing::error!("An error occurred loading the state of {}: {}", guild_id, e);
+                return false;
+            }
+        };
+        self.loaded
+            .lock()
+            .expect("The loaded states mutex was poisoned.")
+            .insert(guild_id, state);
+        senders.remove(&guild_id);
+        true
+    }
+
+    fn sender(&self, guild_id: GuildId) -> mpsc::Sender<Request> {
+        let mut senders = self
+            .senders
+            .lock()
+            .expect("The state senders mutex was poisoned.");
+        senders
+            .entry(guild_id)
+            .or_insert_with(|| {
+                let state = self
+                    .loaded
+                    .lock()
+                    .expect("The loaded states mutex was poisoned.")
+                    .remove(&guild_id)
+                    .unwrap_or_default();
+                let (sender, receiver) = mpsc::channel(QUEUE_CAPACITY);
+                let actor = GuildActor {
+                    guild_id,
+                    storage: self.storage.clone(),
+                    clock: self.clock.clone(),
+                    state,
+                    recent_mentions: HashMap::new(),
+                };
+                tokio::spawn(actor.run(receiver));
+                sender
+            })
+            .clone()
+    }
+}
+
+/// Loads the counts, records and last report of every guild.
+fn load_states(storage: &dyn Storage) -> storage::Result<HashMap<GuildId, GuildState>> {
+    let mut states: HashMap<GuildId, GuildState> = HashMap::new();
+    for (guild_id, record) in storage.load_records()? {
+        states.entry(guild_id).or_default().record = record;
+    }
+    for (guild_id, channel_records) in storage.load_channel_records()? {
+        states.entry(guild_id).or_default().channel_records = channel_records;
+    }
+    for (guild_id, mention_counts) in storage.load_mention_counts()? {
+        states.entry(guild_id).or_default().mention_counts = mention_counts;
+    }
+    for (guild_id, last_report) in storage.load_last_reports()? {
+        states.entry(guild_id).or_default().last_report = Some(last_report);
+    }
+    Ok(states)
+}
+
+/// Applies mentions and corrections from the log, both sorted by their times, on top of a state.
+fn replay(state: &mut GuildState, mentions: Vec<Mention>, corrections: Vec<Correction>) {
+    state.replay(
+        mentions.iter().map(Mention::tally),
+        corrections
+            .into_iter()
+            .map(|correction| (correction.at, correction.kind)),
+    );
+}
+
+/// The task owning the state of a single guild.
+struct GuildActor {
+    guild_id: GuildId,
+    storage: Arc<dyn Storage>,
+    clock: Arc<dyn Clock>,
+    state: GuildState,
+    recent_mentions: HashMap<MessageId, Vec<TrackedMentions>>,
+}
+
+impl GuildActor {
+    async fn run(mut self, mut receiver: mpsc::Receiver<Request>) {
+        while let Some(request) = receiver.recv().await {
+            match request {
+                Request::Track {
+                    mentions,
+                    revertible,
+                    reply,
+                } => {
+                    let _ = reply.send(self.track(mentions, revertible));
+                }
+                Request::Revert {
+                    message_ids,
+                    cutoff,
+                    reply,
+                } => {
+                    let _ = reply.send(self.revert(&message_ids, cutoff).await);
+                }
+                Request::Record { channel_id, reply } => {
+                    let record = match channel_id {
+                        Some(channel_id) => self.state.channel_records.get(&channel_id),
+                        None => Some(&self.state.record),
+                    };
+                    let _ = reply.send(record.cloned().unwrap_or_default());
+                }
+                Request::MentionCounts { reply } => {
+                    let _ = reply.send(self.state.mention_counts.clone());
+                }
+                Request::SetMentionCount {
+                    user_id,
+                    count,
+                    reply,
+                } => {
+                    let _ = reply.send(self.set_mention_count(user_id, count));
+                }
+                Request::SetRecord {
+                    channel_id,
+                    duration,
+                    reply,
+                } => {
+                    let _ = reply.send(self.set_record(channel_id, duration));
+                }
+                Request::Disqualify { message_id, reply } => {
+                    let _ = reply.send(self.disqualify(message_id).await);
+                }
+                Request::Rebuild { rule, reply } => {
+                    let _ = reply.send(self.rebuild_without(rule.as_deref()).await);
+                }
+                Request::Compact { before, reply } => {
+                    let _ = reply.send(self.compact(before).await);
+                }
+                Request::ClaimReport {
+                    schedule,
+                    now,
+                    reply,
+                } => {
+                    let _ = reply.send(self.claim_report(&schedule, now));
+                }
+            }
+        }
+    }
+
+    fn track(&mut self, mentions: Vec<Mention>, revertible: bool) -> Option<Tracked> {
+        let mentions = mentions
+            .into_iter()
+            .filter(|mention| match self.storage.save_mention(mention) {
+                Ok(new) => new,
+                Err(e) => {
+                    tracing::error!("An error occurred saving the mention: {}", e);
+                    true
+                }
+            })
+            .collect::<Vec<_>>();
+        let first = mentions.first()?;
+        let (user_id, channel_id, message_id, sent_at) = (
+            first.user_id,
+            first.channel_id,
+            first.message_id,
+            first.sent_at,
+        );
+        let tracked = self.state.track(&Tally {
+            channel_id,
+            user_id,
+            weight: mentions.iter().map(|mention| mention.weight).sum(),
+            at: sent_at,
+        });
+
+        if let Err(e) =
+            self.storage
+                .save_mention_count(self.guild_id, user_id, tracked.mention_count)
+        {
+            tracing::error!("An error occurred saving the mention count: {}", e);
+        }
+        if let Err(e) = self.storage.save_record(self.guild_id, &self.state.record) {
+            tracing::error!("An error occurred saving the record: {}", e);
+        }
+        if let Err(e) = self.storage.save_channel_record(
+            self.guild_id,
+            channel_id,
+            &self.state.channel_records[&channel_id],
+        ) {
+            tracing::error!("An error occurred saving the channel record: {}", e);
+        }
+
+        if revertible {
+            let cutoff =
+                self.clock.now() - chrono::Duration::seconds(MAX_DELETION_GRACE_SECONDS as i64);
+            self.recent_mentions
+                .retain(|_, tracked| tracked.iter().any(|t| t.sent_at > cutoff));
+            self.recent_mentions
+                .entry(message_id)
+                .or_default()
+                .push(TrackedMentions {
+                    user_id,
+                    sources: mentions.iter().map(|mention| mention.source).collect(),
+                    sent_at,
+                });
+        }
+
+        Some(tracked)
+    }
+
+    /// The streaks the mentions ended are restored by rebuilding without them, joined with the
+    /// ones that began since.
+    async fn revert(&mut self, message_ids: &[MessageId], cutoff: DateTime<Utc>) -> usize {
+        let mut reverted = 0;
+        for &message_id in message_ids {
+            let tracked = match self.recent_mentions.remove(&message_id) {
+                Some(tracked) => tracked,
+                None => continue,
+            };
+            let tracked = tracked
+                .into_iter()
+                .filter(|tracked| tracked.sent_at >= cutoff)
+                .collect::<Vec<_>>();
+            if tracked.is_empty() {
+                continue;
+            }
+
+            for tracked in &tracked {
+                for &source in &tracked.sources {
+                    if let Err(e) = self
+                        .storage
+                        .delete_mention(message_id, tracked.user_id, source)
+                    {
+                        tracing::error!("An error occurred deleting the mention: {}", e);
+                    }
+                }
+            }
+            reverted += 1;
+        }
+
+        if reverted > 0 {
+            self.rebuild().await;
+        }
+        reverted
+    }
+
+    fn set_mention_count(&mut self, user_id: UserId, count: usize) -> usize {
+        let previous = self
+            .state
+            .mention_counts
+            .get(&user_id)
+            .copied()
+            .unwrap_or_default();
+        self.correct(CorrectionKind::MentionCount { user_id, count });
+        previous
+    }
+
+    fn set_record(
+        &mut self,
+        channel_id: Option<ChannelId>,
+        duration: Option<Duration>,
+    ) -> Option<Duration> {
+        let previous = match channel_id {
+            Some(channel_id) => self
+                .state
+                .channel_records
+                .get(&channel_id)
+                .and_then(|record| record.duration),
+            None => self.state.record.duration,
+        };
+        self.correct(CorrectionKind::Record {
+            channel_id,
+            duration,
+        });
+        previous
+    }
+
+    /// Saves a correction to the log and applies it.
+    fn correct(&mut self, kind: CorrectionKind) {
+        let correction = Correction {
+            guild_id: self.guild_id,
+            kind,
+            at: self.clock.now(),
+        };
+        if let Err(e) = self.storage.save_correction(&correction) {
+            tracing::error!("An error occurred saving the correction: {}", e);
+        }
+
+        let previous = self.state.clone();
+        self.state.correct(&correction.kind);
+        self.save_changes(&previous);
+    }
+
+    async fn disqualify(&mut self, message_id: MessageId) -> Option<Disqualified> {
+        let mentions = match self.storage.load_message_mentions(message_id) {
+            Ok(mentions) => mentions,
+            Err(e) => {
+                tracing::error!("An error occurred loading the mentions of a message: {}", e);
+                return None;
+            }
+        };
+        let mentions = mentions
+            .into_iter()
+            .filter(|mention| mention.guild_id == self.guild_id)
+            .collect::<Vec<_>>();
+        let channel_id = mentions.first()?.channel_id;
+        self.recent_mentions.remove(&message_id);
+
+        let mut weights = HashMap::new();
+        for mention in &mentions {
+            if let Err(e) = self
+                .storage
+                .delete_mention(message_id, mention.user_id, mention.source)
+            {
+                tracing::error!("An error occurred deleting the mention: {}", e);
+            }
+            *weights.entry(mention.user_id).or_default() += mention.weight;
+        }
+        self.rebuild().await;
+
+        Some(Disqualified {
+            channel_id,
+            weights,
+        })
+    }
+
+    async fn rebuild_without(&mut self, rule: Option<&str>) -> Option<usize> {
+        let disqualified = match rule {
+            Some(rule) => match self.storage.delete_rule_mentions(self.guild_id, rule) {
+                Ok(disqualified) => disqualified,
+                Err(e) => {
+                    tracing::error!("An error occurred deleting the mentions of a rule: {}", e);
+                    return None;
+                }
+            },
+            None => 0,
+        };
+        self.rebuild().await.then_some(disqualified)
+    }
+
+    /// Replaces the counts and records with the ones rebuilt from the baseline and the log,
+    /// returning whether it could be read.
+    ///
+    /// The log is read and replayed on a blocking thread, as it can grow large.
+    async fn rebuild(&mut self) -> bool {
+        let storage = self.storage.clone();
+        let guild_id = self.guild_id;
+        let rebuilt = tokio::task::spawn_blocking(move || -> storage::Result<GuildState> {
+            let mut state = storage.load_baseline(guild_id)?;
+            replay(
+                &mut state,
+                storage.load_mentions(guild_id)?,
+                storage.load_corrections(guild_id)?,
+            );
+            Ok(state)
+        })
+        .await;
+        let mut state = match rebuilt {
+            Ok(Ok(state)) => state,
+            Ok(Err(e)) => {
+                tracing::error!("An error occurred loading the log: {}", e);
+                return false;
+            }
+            Err(e) => {
+                tracing::error!("An error occurred rebuilding from the log: {}", e);
+                return false;
+            }
+        };
+
+        state.last_report = self.state.last_report;
+        let previous = std::mem::replace(&mut self.state, state);
+        self.save_changes(&previous);
+        true
+    }
+
+    /// Saves the counts and records that differ from `previous`.
+    fn save_changes(&self, previous: &GuildState) {
+        let users = previous
+            .mention_counts
+            .keys()
+            .chain(self.state.mention_counts.keys())
+            .collect::<HashSet<_>>();
+        for &user_id in users {
+            let count = self
+                .state
+                .mention_counts
+                .get(&user_id)
+                .copied()
+                .unwrap_or_default();
+            if previous.mention_counts.get(&user_id) != Some(&count) {
+                if let Err(e) = self
+                    .storage
+                    .save_mention_count(self.guild_id, user_id, count)
+                {
+                    tracing::error!("An error occurred saving the mention count: {}", e);
+                }
+            }
+        }
+
+        if previous.record != self.state.record {
+            if let Err(e) = self.storage.save_record(self.guild_id, &self.state.record) {
+                tracing::error!("An error occurred saving the record: {}", e);
+            }
+        }
+
+        let channels = previous
+            .channel_records
+            .keys()
+            .chain(self.state.channel_records.keys())
+            .collect::<HashSet<_>>();
+        for &channel_id in channels {
+            let record = self
+                .state
+                .channel_records
+                .get(&channel_id)
+                .cloned()
+                .unwrap_or_default();
+            if previous.channel_records.get(&channel_id) != Some(&record) {
+                if let Err(e) = self
+                    .storage
+                    .save_channel_record(self.guild_id, channel_id, &record)
+                {
+                    tracing::error!("An error occurred saving the channel record: {}", e);
+                }
+            }
+        }
+    }
+
+    /// The counts and records stay the same, as the baseline adds up to what was folded into it.
+    async fn compact(&mut self, before: DateTime<Utc>) -> usize {
+        let storage = self.storage.clone();
+        let guild_id = self.guild_id;
+        let compacted = tokio::task::spawn_blocking(move || -> storage::Result<usize> {
+            let mut baseline = storage.load_baseline(guild_id)?;
+            let mentions = storage
+                .load_mentions(guild_id)?
+                .into_iter()
+                .filter(|mention| mention.sent_at < before)
+                .collect();
+            let corrections = storage
+                .load_corrections(guild_id)?
+                .into_iter()
+                .filter(|correction| correction.at < before)
+                .collect();
+            replay(&mut baseline, mentions, corrections);
+            storage.compact(guild_id, &baseline, before)
+        })
+        .await;
+
+        match compacted {
+            Ok(Ok(compacted)) => compacted,
+            Ok(Err(e)) => {
+                tracing::error!("An error occurred compacting the log: {}", e);
+                0
+            }
+            Err(e) => {
+                tracing::error!("An error occurred compacting the log: {}", e);
+                0
+            }
+        }
+    }
+
+    fn claim_report(&mut self, schedule: &ReportSchedule, now: DateTime<Utc>) -> bool {
+        let is_due = match self.state.last_report {
+            Some(last_report) if schedule.is_due(last_report, now) => true,
+            Some(_) => return false,
+            None => false,
+        };
+
+        self.state.last_report = Some(now);
+        if let Err(e) = self.storage.save_last_report(self.guild_id, now) {
+            tracing::error!("An error occurred saving the last report time: {}", e);
+        }
+        is_due
+    }
+}
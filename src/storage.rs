@@ -0,0 +1,849 @@
+use std::{collections::HashMap, fmt, path::Path, sync::Mutex, time::Duration};
+
+use chrono::{DateTime, TimeZone, Utc};
+use crabe_core::{
+    record::Record,
+    scheduler::{JobRuns, JobRunsError},
+    sources::MentionSource,
+};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde_json::Value;
+use serenity::model::prelude::{ChannelId, GuildId, MessageId, UserId};
+
+use crate::{
+    audit::AuditEntry,
+    settings::Overrides,
+    state::{Correction, CorrectionKind, GuildState, Mention},
+};
+
+#[derive(Debug)]
+pub enum StorageError {
+    Sqlite(rusqlite::Error),
+    Serialization(serde_json::Error),
+}
+
+impl fmt::Display for StorageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StorageError::Sqlite(e) => write!(f, "SQLite error: {}", e),
+            StorageError::Serialization(e) => write!(f, "Serialization error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for StorageError {}
+
+impl From<rusqlite::Error> for StorageError {
+    fn from(e: rusqlite::Error) -> Self {
+        StorageError::Sqlite(e)
+    }
+}
+
+impl From<serde_json::Error> for StorageError {
+    fn from(e: serde_json::Error) -> Self {
+        StorageError::Serialization(e)
+    }
+}
+
+pub type Result<T> = std::result::Result<T, StorageError>;
+
+/// Persists the tracked state so that it survives restarts of the bot.
+pub trait Storage: JobRuns {
+    fn load_records(&self) -> Result<HashMap<GuildId, Record>>;
+    fn load_channel_records(&self) -> Result<HashMap<GuildId, HashMap<ChannelId, Record>>>;
+    fn load_mention_counts(&self) -> Result<HashMap<GuildId, HashMap<UserId, usize>>>;
+    fn load_last_reports(&self) -> Result<HashMap<GuildId, DateTime<Utc>>>;
+    /// Counts the mentions per user in the half-open range `[from, to)`.
+    fn load_mention_counts_between(
+        &self,
+        guild_id: GuildId,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<HashMap<UserId, usize>>;
+    /// Loads the mentions made in, or as reactions to, a message.
+    fn load_message_mentions(&self, message_id: MessageId) -> Result<Vec<Mention>>;
+    /// Loads the log of all mentions of a guild that weren't compacted yet, the oldest first.
+    fn load_mentions(&self, guild_id: GuildId) -> Result<Vec<Mention>>;
+    /// Loads the corrections admins made to a guild that weren't compacted yet, the oldest first.
+    fn load_corrections(&self, guild_id: GuildId) -> Result<Vec<Correction>>;
+    /// Loads the counts and records the compacted part of the log of a guild added up to, which
+    /// are empty until it was first compacted.
+    fn load_baseline(&self, guild_id: GuildId) -> Result<GuildState>;
+    /// Loads the settings a guild changed through the commands, which are none until they were
+    /// first saved.
+    fn load_setting_overrides(&self, guild_id: GuildId) -> Result<Overrides>;
+    /// Loads the most recent entries of the audit log of a guild, the newest first.
+    fn load_audit_entries(&self, guild_id: GuildId, limit: usize) -> Result<Vec<AuditEntry>>;
+
+    fn save_record(&self, guild_id: GuildId, record: &Record) -> Result<()>;
+    fn save_channel_record(
+        &self,
+        guild_id: GuildId,
+        channel_id: ChannelId,
+        record: &Record,
+    ) -> Result<()>;
+    fn save_mention_count(&self, guild_id: GuildId, user_id: UserId, count: usize) -> Result<()>;
+    fn save_last_report(&self, guild_id: GuildId, last_report: DateTime<Utc>) -> Result<()>;
+    /// Saves a mention unless it was already saved before, returning whether it is new.
+    fn save_mention(&self, mention: &Mention) -> Result<bool>;
+    fn save_setting_overrides(&self, guild_id: GuildId, overrides: &Overrides) -> Result<()>;
+    fn save_audit_entry(&self, entry: &AuditEntry) -> Result<()>;
+    fn save_correction(&self, correction: &Correction) -> Result<()>;
+
+    /// Replaces the baseline of a guild and deletes the mentions and corrections before the given
+    /// time, which it must include, returning how many mentions there were.
+    fn compact(
+        &self,
+        guild_id: GuildId,
+        baseline: &GuildState,
+        before: DateTime<Utc>,
+    ) -> Result<usize>;
+
+    /// Deletes the mention a user made from a single source in a message.
+    fn delete_mention(
+        &self,
+        message_id: MessageId,
+        user_id: UserId,
+        source: MentionSource,
+    ) -> Result<()>;
+    /// Deletes the mentions of a guild written in text matching a keyword rule, returning how many
+    /// there were.
+    fn delete_rule_mentions(&self, guild_id: GuildId, rule: &str) -> Result<usize>;
+
+    /// Makes sure everything written so far is durably stored.
+    fn flush(&self) -> Result<()>;
+}
+
+/// The changes to the database schema, applied in order and tracked in SQLite's `user_version`.
+const MIGRATIONS: &[&str] = &[
+    "CREATE TABLE IF NOT EXISTS records (
+            guild_id INTEGER PRIMARY KEY,
+            last_mention INTEGER,
+            duration INTEGER
+        );
+        CREATE TABLE IF NOT EXISTS mention_counts (
+            guild_id INTEGER NOT NULL,
+            user_id INTEGER NOT NULL,
+            count INTEGER NOT NULL,
+            PRIMARY KEY (guild_id, user_id)
+        );
+        CREATE TABLE IF NOT EXISTS last_reports (
+            guild_id INTEGER PRIMARY KEY,
+            last_report INTEGER NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS mentions (
+            message_id INTEGER PRIMARY KEY,
+            guild_id INTEGER NOT NULL,
+            channel_id INTEGER NOT NULL,
+            user_id INTEGER NOT NULL,
+            sent_at INTEGER NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS mentions_by_guild_and_time ON mentions (guild_id, sent_at);
+        CREATE TABLE IF NOT EXISTS guild_settings (
+            guild_id INTEGER PRIMARY KEY,
+            settings TEXT NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS job_runs (
+            name TEXT PRIMARY KEY,
+            last_run INTEGER NOT NULL
+        );",
+    "ALTER TABLE mentions RENAME TO mentions_without_sources;
+    DROP INDEX mentions_by_guild_and_time;
+    CREATE TABLE mentions (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        guild_id INTEGER NOT NULL,
+        channel_id INTEGER NOT NULL,
+        user_id INTEGER NOT NULL,
+        message_id INTEGER NOT NULL,
+        source TEXT NOT NULL,
+        weight INTEGER NOT NULL,
+        sent_at INTEGER NOT NULL,
+        UNIQUE (message_id, user_id, source)
+    );
+    INSERT INTO mentions (guild_id, channel_id, user_id, message_id, source, weight, sent_at)
+    SELECT guild_id, channel_id, user_id, message_id, 'text', 1, sent_at
+    FROM mentions_without_sources;
+    DROP TABLE mentions_without_sources;
+    CREATE INDEX mentions_by_guild_and_time ON mentions (guild_id, sent_at);",
+    "CREATE TABLE channel_records (
+        guild_id INTEGER NOT NULL,
+        channel_id INTEGER NOT NULL,
+        last_mention INTEGER,
+        duration INTEGER,
+        PRIMARY KEY (guild_id, channel_id)
+    );",
+    // Only the settings a guild changed are stored from now on, so the layers beneath, such as the
+    // config file, still apply to the rest. The settings saved so far are reduced to the values
+    // differing from the defaults at the time.
+    r#"UPDATE guild_settings SET settings = (
+        SELECT json_group_object(
+            stored.key,
+            CASE stored.type
+                WHEN 'true' THEN json('true')
+                WHEN 'false' THEN json('false')
+                WHEN 'object' THEN json(stored.value)
+                WHEN 'array' THEN json(stored.value)
+                ELSE stored.value
+            END
+        )
+        FROM json_each(guild_settings.settings) AS stored
+        WHERE stored.value IS NOT json_extract(
+            '{"report_channel":null,"report_schedule":"0 9 * * Mon","timezone":"UTC","announce_milestones":true,"announce_records":"server","mention_retention_days":null,"keywords":{"include":[{"pattern":"rust","mode":"whole_word"}],"exclude":[]},"regions":{"plain":true,"quotes":false,"code_blocks":false,"inline_code":true,"spoilers":true,"links":false},"normalization":{"nfkc":true,"strip_invisible":true,"confusables":true,"collapse_spacing":false,"leetspeak":false},"sources":{"text":{"enabled":true,"weight":1},"emoji":{"enabled":true,"weight":1},"stickers":{"enabled":true,"weight":1},"reactions":{"enabled":true,"weight":1},"names":["rust","ferris"],"ids":[],"unicode_emoji":["🦀"]},"deletion_grace_seconds":30,"ignore":{"bots":true,"webhooks":true,"system_messages":true,"users":[],"roles":[]},"channels":{"include":[],"exclude":[]}}',
+            '$."' || stored.key || '"'
+        )
+    );"#,
+    "CREATE TABLE IF NOT EXISTS audit_log (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        guild_id INTEGER NOT NULL,
+        actor_id INTEGER NOT NULL,
+        action TEXT NOT NULL,
+        target TEXT NOT NULL,
+        old_value TEXT NOT NULL,
+        new_value TEXT NOT NULL,
+        at INTEGER NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS audit_log_by_guild ON audit_log (guild_id, id);",
+    // The mentions become the log the counts and records are derived from. What they added up to
+    // before is kept as the baseline the log is replayed on, with the counts reduced by the
+    // mentions still in the log. The records are kept as they are, as the streaks they were
+    // built from can't be recovered.
+    "ALTER TABLE mentions RENAME TO mentions_without_rules;
+    DROP INDEX mentions_by_guild_and_time;
+    CREATE TABLE mentions (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        guild_id INTEGER NOT NULL,
+        channel_id INTEGER NOT NULL,
+        user_id INTEGER NOT NULL,
+        message_id INTEGER NOT NULL,
+        source TEXT NOT NULL,
+        weight INTEGER NOT NULL,
+        sent_at INTEGER NOT NULL,
+        rule TEXT,
+        UNIQUE (message_id, user_id, source)
+    );
+    INSERT INTO mentions (id, guild_id, channel_id, user_id, message_id, source, weight, sent_at)
+    SELECT id, guild_id, channel_id, user_id, message_id, source, weight, sent_at
+    FROM mentions_without_rules;
+    DROP TABLE mentions_without_rules;
+    CREATE INDEX mentions_by_guild_and_time ON mentions (guild_id, sent_at);
+    CREATE TABLE IF NOT EXISTS corrections (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        guild_id INTEGER NOT NULL,
+        kind TEXT NOT NULL,
+        target INTEGER,
+        value INTEGER NOT NULL,
+        at INTEGER NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS corrections_by_guild_and_time ON corrections (guild_id, at);
+    CREATE TABLE IF NOT EXISTS baseline_counts (
+        guild_id INTEGER NOT NULL,
+        user_id INTEGER NOT NULL,
+        count INTEGER NOT NULL,
+        PRIMARY KEY (guild_id, user_id)
+    );
+    CREATE TABLE IF NOT EXISTS baseline_records (
+        guild_id INTEGER NOT NULL,
+        channel_id INTEGER NOT NULL,
+        last_mention INTEGER,
+        duration INTEGER,
+        PRIMARY KEY (guild_id, channel_id)
+    );
+    INSERT OR REPLACE INTO baseline_counts (guild_id, user_id, count)
+    SELECT counts.guild_id, counts.user_id, counts.count - COALESCE(logged.count, 0)
+    FROM mention_counts AS counts
+    LEFT JOIN (
+        SELECT guild_id, user_id, SUM(weight) AS count FROM mentions GROUP BY guild_id, user_id
+    ) AS logged USING (guild_id, user_id)
+    WHERE counts.count > COALESCE(logged.count, 0);
+    INSERT OR REPLACE INTO baseline_records (guild_id, channel_id, last_mention, duration)
+    SELECT guild_id, 0, last_mention, duration FROM records
+    UNION ALL
+    SELECT guild_id, channel_id, last_mention, duration FROM channel_records;",
+];
+
+/// The channel the baseline record of a whole guild is stored for.
+const GUILD_BASELINE_CHANNEL: i64 = 0;
+
+pub struct SqliteStorage {
+    connection: Mutex<Connection>,
+}
+
+impl SqliteStorage {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let mut connection = Connection::open(path)?;
+        connection.pragma_update(None, "journal_mode", "WAL")?;
+
+        let version =
+            connection.query_row("PRAGMA user_version", [], |row| row.get::<_, i64>(0))?;
+        for (index, migration) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+            let transaction = connection.transaction()?;
+            transaction.execute_batch(migration)?;
+            transaction.pragma_update(None, "user_version", index as i64 + 1)?;
+            transaction.commit()?;
+        }
+
+        Ok(Self {
+            connection: Mutex::new(connection),
+        })
+    }
+
+    fn connection(&self) -> std::sync::MutexGuard<'_, Connection> {
+        self.connection
+            .lock()
+            .expect("The SQLite connection mutex was poisoned.")
+    }
+}
+
+// Discord snowflakes and timestamps are stored in SQLite's signed 64-bit integers.
+fn to_sql_id(id: u64) -> i64 {
+    id as i64
+}
+
+fn from_sql_id(id: i64) -> u64 {
+    id as u64
+}
+
+fn to_millis(time: DateTime<Utc>) -> i64 {
+    time.timestamp_millis()
+}
+
+fn from_millis(millis: i64) -> DateTime<Utc> {
+    Utc.timestamp_millis_opt(millis)
+        .single()
+        .unwrap_or(DateTime::<Utc>::MIN_UTC)
+}
+
+/// The columns of a mention as they are read from a row, before its source is decoded.
+type MentionRow = (
+    GuildId,
+    ChannelId,
+    UserId,
+    MessageId,
+    String,
+    usize,
+    DateTime<Utc>,
+    Option<String>,
+);
+
+fn mention_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<MentionRow> {
+    Ok((
+        GuildId(from_sql_id(row.get(0)?)),
+        ChannelId(from_sql_id(row.get(1)?)),
+        UserId(from_sql_id(row.get(2)?)),
+        MessageId(from_sql_id(row.get(3)?)),
+        row.get(4)?,
+        row.get::<_, i64>(5)?.max(0) as usize,
+        from_millis(row.get(6)?),
+        row.get(7)?,
+    ))
+}
+
+fn to_mention(
+    (guild_id, channel_id, user_id, message_id, source, weight, sent_at, rule): MentionRow,
+) -> Result<Mention> {
+    Ok(Mention {
+        guild_id,
+        channel_id,
+        user_id,
+        message_id,
+        source: serde_json::from_value(Value::String(source))?,
+        weight,
+        sent_at,
+        rule,
+    })
+}
+
+impl Storage for SqliteStorage {
+    fn load_records(&self) -> Result<HashMap<GuildId, Record>> {
+        let connection = self.connection();
+        let mut statement =
+            connection.prepare("SELECT guild_id, last_mention, duration FROM records")?;
+        let rows = statement.query_map([], |row| {
+            let guild_id = GuildId(from_sql_id(row.get(0)?));
+            let last_mention = row.get::<_, Option<i64>>(1)?.map(from_millis);
+            let duration = row
+                .get::<_, Option<i64>>(2)?
+                .map(|millis| Duration::from_millis(millis.max(0) as u64));
+            Ok((
+                guild_id,
+                Record {
+                    last_mention,
+                    duration,
+                },
+            ))
+        })?;
+
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    fn load_channel_records(&self) -> Result<HashMap<GuildId, HashMap<ChannelId, Record>>> {
+        let connection = self.connection();
+        let mut statement = connection
+            .prepare("SELECT guild_id, channel_id, last_mention, duration FROM channel_records")?;
+        let mut rows = statement.query([])?;
+
+        let mut records: HashMap<GuildId, HashMap<ChannelId, Record>> = HashMap::new();
+        while let Some(row) = rows.next()? {
+            let guild_id = GuildId(from_sql_id(row.get(0)?));
+            let channel_id = ChannelId(from_sql_id(row.get(1)?));
+            let last_mention = row.get::<_, Option<i64>>(2)?.map(from_millis);
+            let duration = row
+                .get::<_, Option<i64>>(3)?
+                .map(|millis| Duration::from_millis(millis.max(0) as u64));
+            records.entry(guild_id).or_default().insert(
+                channel_id,
+                Record {
+                    last_mention,
+                    duration,
+                },
+            );
+        }
+
+        Ok(records)
+    }
+
+    fn load_mention_counts(&self) -> Result<HashMap<GuildId, HashMap<UserId, usize>>> {
+        let connection = self.connection();
+        let mut statement =
+            connection.prepare("SELECT guild_id, user_id, count FROM mention_counts")?;
+        let mut rows = statement.query([])?;
+
+        let mut counts: HashMap<GuildId, HashMap<UserId, usize>> = HashMap::new();
+        while let Some(row) = rows.next()? {
+            let guild_id = GuildId(from_sql_id(row.get(0)?));
+            let user_id = UserId(from_sql_id(row.get(1)?));
+            let count = row.get::<_, i64>(2)?.max(0) as usize;
+            counts.entry(guild_id).or_default().insert(user_id, count);
+        }
+
+        Ok(counts)
+    }
+
+    fn load_last_reports(&self) -> Result<HashMap<GuildId, DateTime<Utc>>> {
+        let connection = self.connection();
+        let mut statement = connection.prepare("SELECT guild_id, last_report FROM last_reports")?;
+        let rows = statement.query_map([], |row| {
+            Ok((GuildId(from_sql_id(row.get(0)?)), from_millis(row.get(1)?)))
+        })?;
+
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    fn load_mention_counts_between(
+        &self,
+        guild_id: GuildId,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<HashMap<UserId, usize>> {
+        let connection = self.connection();
+        let mut statement = connection.prepare(
+            "SELECT user_id, SUM(weight) FROM mentions
+            WHERE guild_id = ?1 AND sent_at >= ?2 AND sent_at < ?3
+            GROUP BY user_id",
+        )?;
+        let rows = statement.query_map(
+            params![to_sql_id(guild_id.0), to_millis(from), to_millis(to)],
+            |row| {
+                Ok((
+                    UserId(from_sql_id(row.get(0)?)),
+                    row.get::<_, i64>(1)?.max(0) as usize,
+                ))
+            },
+        )?;
+
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    fn load_message_mentions(&self, message_id: MessageId) -> Result<Vec<Mention>> {
+        let connection = self.connection();
+        let mut statement = connection.prepare(
+            "SELECT guild_id, channel_id, user_id, message_id, source, weight, sent_at, rule
+            FROM mentions WHERE message_id = ?1",
+        )?;
+        let rows = statement.query_map(params![to_sql_id(message_id.0)], mention_row)?;
+        rows.map(|row| to_mention(row?)).collect()
+    }
+
+    fn load_mentions(&self, guild_id: GuildId) -> Result<Vec<Mention>> {
+        let connection = self.connection();
+        let mut statement = connection.prepare(
+            "SELECT guild_id, channel_id, user_id, message_id, source, weight, sent_at, rule
+            FROM mentions WHERE guild_id = ?1
+            ORDER BY sent_at, id",
+        )?;
+        let rows = statement.query_map(params![to_sql_id(guild_id.0)], mention_row)?;
+        rows.map(|row| to_mention(row?)).collect()
+    }
+
+    fn load_corrections(&self, guild_id: GuildId) -> Result<Vec<Correction>> {
+        let connection = self.connection();
+        let mut statement = connection.prepare(
+            "SELECT kind, target, value, at FROM corrections WHERE guild_id = ?1
+            ORDER BY at, id",
+        )?;
+        let rows = statement.query_map(params![to_sql_id(guild_id.0)], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, Option<i64>>(1)?.map(from_sql_id),
+                row.get::<_, i64>(2)?.max(0) as u64,
+                from_millis(row.get(3)?),
+            ))
+        })?;
+
+        let mut corrections = Vec::new();
+        for row in rows {
+            let (kind, target, value, at) = row?;
+            let kind = match (kind.as_str(), target) {
+                ("mention_count", Some(user_id)) => CorrectionKind::MentionCount {
+                    user_id: UserId(user_id),
+                    count: value as usize,
+                },
+                // A cleared record is saved as a duration of zero.
+                ("record", channel_id) => CorrectionKind::Record {
+                    channel_id: channel_id.map(ChannelId),
+                    duration: Some(Duration::from_millis(value)).filter(|d| !d.is_zero()),
+                },
+                _ => {
+                    tracing::warn!("Skipping a correction of {} of an unknown kind.", guild_id);
+                    continue;
+                }
+            };
+            corrections.push(Correction { guild_id, kind, at });
+        }
+        Ok(corrections)
+    }
+
+    fn load_baseline(&self, guild_id: GuildId) -> Result<GuildState> {
+        let connection = self.connection();
+        let mut baseline = GuildState::default();
+
+        let mut statement =
+            connection.prepare("SELECT user_id, count FROM baseline_counts WHERE guild_id = ?1")?;
+        let rows = statement.query_map(params![to_sql_id(guild_id.0)], |row| {
+            Ok((
+                UserId(from_sql_id(row.get(0)?)),
+                row.get::<_, i64>(1)?.max(0) as usize,
+            ))
+        })?;
+        baseline.mention_counts = rows.collect::<rusqlite::Result<_>>()?;
+
+        let mut statement = connection.prepare(
+            "SELECT channel_id, last_mention, duration FROM baseline_records WHERE guild_id = ?1",
+        )?;
+        let mut rows = statement.query(params![to_sql_id(guild_id.0)])?;
+        while let Some(row) = rows.next()? {
+            let channel_id = row.get::<_, i64>(0)?;
+            let record = Record {
+                last_mention: row.get::<_, Option<i64>>(1)?.map(from_millis),
+                duration: row
+                    .get::<_, Option<i64>>(2)?
+                    .map(|millis| Duration::from_millis(millis.max(0) as u64)),
+            };
+            if channel_id == GUILD_BASELINE_CHANNEL {
+                baseline.record = record;
+            } else {
+                baseline
+                    .channel_records
+                    .insert(ChannelId(from_sql_id(channel_id)), record);
+            }
+        }
+
+        Ok(baseline)
+    }
+
+    fn load_setting_overrides(&self, guild_id: GuildId) -> Result<Overrides> {
+        let settings = self
+            .connection()
+            .query_row(
+                "SELECT settings FROM guild_settings WHERE guild_id = ?1",
+                params![to_sql_id(guild_id.0)],
+                |row| row.get::<_, String>(0),
+            )
+            .optional()?;
+
+        match settings {
+            Some(settings) => Ok(serde_json::from_str(&settings)?),
+            None => Ok(Overrides::new()),
+        }
+    }
+
+    fn load_audit_entries(&self, guild_id: GuildId, limit: usize) -> Result<Vec<AuditEntry>> {
+        let connection = self.connection();
+        let mut statement = connection.prepare(
+            "SELECT actor_id, action, target, old_value, new_value, at FROM audit_log
+            WHERE guild_id = ?1
+            ORDER BY id DESC
+            LIMIT ?2",
+        )?;
+        let rows = statement.query_map(params![to_sql_id(guild_id.0), limit as i64], |row| {
+            Ok((
+                UserId(from_sql_id(row.get(0)?)),
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, String>(3)?,
+                row.get::<_, String>(4)?,
+                from_millis(row.get(5)?),
+            ))
+        })?;
+
+        let mut entries = Vec::new();
+        for row in rows {
+            let (actor_id, action, target, old_value, new_value, at) = row?;
+            entries.push(AuditEntry {
+                guild_id,
+                actor_id,
+                action: serde_json::from_value(Value::String(action))?,
+                target,
+                old_value: serde_json::from_str(&old_value)?,
+                new_value: serde_json::from_str(&new_value)?,
+                at,
+            });
+        }
+        Ok(entries)
+    }
+
+    fn save_record(&self, guild_id: GuildId, record: &Record) -> Result<()> {
+        self.connection().execute(
+            "INSERT INTO records (guild_id, last_mention, duration) VALUES (?1, ?2, ?3)
+            ON CONFLICT (guild_id) DO UPDATE SET
+                last_mention = excluded.last_mention,
+                duration = excluded.duration",
+            params![
+                to_sql_id(guild_id.0),
+                record.last_mention.map(to_millis),
+                record.duration.map(|duration| duration.as_millis() as i64),
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn save_channel_record(
+        &self,
+        guild_id: GuildId,
+        channel_id: ChannelId,
+        record: &Record,
+    ) -> Result<()> {
+        self.connection().execute(
+            "INSERT INTO channel_records (guild_id, channel_id, last_mention, duration)
+            VALUES (?1, ?2, ?3, ?4)
+            ON CONFLICT (guild_id, channel_id) DO UPDATE SET
+                last_mention = excluded.last_mention,
+                duration = excluded.duration",
+            params![
+                to_sql_id(guild_id.0),
+                to_sql_id(channel_id.0),
+                record.last_mention.map(to_millis),
+                record.duration.map(|duration| duration.as_millis() as i64),
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn save_mention_count(&self, guild_id: GuildId, user_id: UserId, count: usize) -> Result<()> {
+        self.connection().execute(
+            "INSERT INTO mention_counts (guild_id, user_id, count) VALUES (?1, ?2, ?3)
+            ON CONFLICT (guild_id, user_id) DO UPDATE SET count = excluded.count",
+            params![to_sql_id(guild_id.0), to_sql_id(user_id.0), count as i64],
+        )?;
+        Ok(())
+    }
+
+    fn save_last_report(&self, guild_id: GuildId, last_report: DateTime<Utc>) -> Result<()> {
+        self.connection().execute(
+            "INSERT INTO last_reports (guild_id, last_report) VALUES (?1, ?2)
+            ON CONFLICT (guild_id) DO UPDATE SET last_report = excluded.last_report",
+            params![to_sql_id(guild_id.0), to_millis(last_report)],
+        )?;
+        Ok(())
+    }
+
+    fn save_mention(&self, mention: &Mention) -> Result<bool> {
+        let inserted = self.connection().execute(
+            "INSERT OR IGNORE INTO mentions
+                (guild_id, channel_id, user_id, message_id, source, weight, sent_at, rule)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                to_sql_id(mention.guild_id.0),
+                to_sql_id(mention.channel_id.0),
+                to_sql_id(mention.user_id.0),
+                to_sql_id(mention.message_id.0),
+                mention.source.as_str(),
+                mention.weight as i64,
+                to_millis(mention.sent_at),
+                mention.rule,
+            ],
+        )?;
+        Ok(inserted > 0)
+    }
+
+    fn save_setting_overrides(&self, guild_id: GuildId, overrides: &Overrides) -> Result<()> {
+        self.connection().execute(
+            "INSERT INTO guild_settings (guild_id, settings) VALUES (?1, ?2)
+            ON CONFLICT (guild_id) DO UPDATE SET settings = excluded.settings",
+            params![to_sql_id(guild_id.0), serde_json::to_string(overrides)?],
+        )?;
+        Ok(())
+    }
+
+    fn save_audit_entry(&self, entry: &AuditEntry) -> Result<()> {
+        let action = match serde_json::to_value(entry.action)? {
+            Value::String(action) => action,
+            _ => unreachable!("The actions are always serialized as strings."),
+        };
+        self.connection().execute(
+            "INSERT INTO audit_log (guild_id, actor_id, action, target, old_value, new_value, at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                to_sql_id(entry.guild_id.0),
+                to_sql_id(entry.actor_id.0),
+                action,
+                entry.target,
+                serde_json::to_string(&entry.old_value)?,
+                serde_json::to_string(&entry.new_value)?,
+                to_millis(entry.at),
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn save_correction(&self, correction: &Correction) -> Result<()> {
+        let (kind, target, value) = match correction.kind {
+            CorrectionKind::MentionCount { user_id, count } => {
+                ("mention_count", Some(user_id.0), count as i64)
+            }
+            CorrectionKind::Record {
+                channel_id,
+                duration,
+            } => (
+                "record",
+                channel_id.map(|channel_id| channel_id.0),
+                duration.map_or(0, |duration| duration.as_millis() as i64),
+            ),
+        };
+        self.connection().execute(
+            "INSERT INTO corrections (guild_id, kind, target, value, at)
+            VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                to_sql_id(correction.guild_id.0),
+                kind,
+                target.map(to_sql_id),
+                value,
+                to_millis(correction.at),
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn compact(
+        &self,
+        guild_id: GuildId,
+        baseline: &GuildState,
+        before: DateTime<Utc>,
+    ) -> Result<usize> {
+        let mut connection = self.connection();
+        let transaction = connection.transaction()?;
+        let guild = to_sql_id(guild_id.0);
+
+        transaction.execute(
+            "DELETE FROM baseline_counts WHERE guild_id = ?1",
+            params![guild],
+        )?;
+        for (user_id, &count) in &baseline.mention_counts {
+            transaction.execute(
+                "INSERT INTO baseline_counts (guild_id, user_id, count) VALUES (?1, ?2, ?3)",
+                params![guild, to_sql_id(user_id.0), count as i64],
+            )?;
+        }
+
+        transaction.execute(
+            "DELETE FROM baseline_records WHERE guild_id = ?1",
+            params![guild],
+        )?;
+        let records = baseline
+            .channel_records
+            .iter()
+            .map(|(channel_id, record)| (to_sql_id(channel_id.0), record))
+            .chain([(GUILD_BASELINE_CHANNEL, &baseline.record)]);
+        for (channel_id, record) in records {
+            transaction.execute(
+                "INSERT INTO baseline_records (guild_id, channel_id, last_mention, duration)
+                VALUES (?1, ?2, ?3, ?4)",
+                params![
+                    guild,
+                    channel_id,
+                    record.last_mention.map(to_millis),
+                    record.duration.map(|duration| duration.as_millis() as i64),
+                ],
+            )?;
+        }
+
+        let deleted = transaction.execute(
+            "DELETE FROM mentions WHERE guild_id = ?1 AND sent_at < ?2",
+            params![guild, to_millis(before)],
+        )?;
+        transaction.execute(
+            "DELETE FROM corrections WHERE guild_id = ?1 AND at < ?2",
+            params![guild, to_millis(before)],
+        )?;
+        transaction.commit()?;
+
+        Ok(deleted)
+    }
+
+    fn delete_mention(
+        &self,
+        message_id: MessageId,
+        user_id: UserId,
+        source: MentionSource,
+    ) -> Result<()> {
+        self.connection().execute(
+            "DELETE FROM mentions WHERE message_id = ?1 AND user_id = ?2 AND source = ?3",
+            params![
+                to_sql_id(message_id.0),
+                to_sql_id(user_id.0),
+                source.as_str()
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn delete_rule_mentions(&self, guild_id: GuildId, rule: &str) -> Result<usize> {
+        Ok(self.connection().execute(
+            "DELETE FROM mentions WHERE guild_id = ?1 AND source = ?2 AND rule = ?3",
+            params![to_sql_id(guild_id.0), MentionSource::Text.as_str(), rule],
+        )?)
+    }
+
+    fn flush(&self) -> Result<()> {
+        self.connection()
+            .execute_batch("PRAGMA wal_checkpoint(TRUNCATE);")?;
+        Ok(())
+    }
+}
+
+impl JobRuns for SqliteStorage {
+    fn load_job_run(&self, name: &str) -> std::result::Result<Option<DateTime<Utc>>, JobRunsError> {
+        Ok(self
+            .connection()
+            .query_row(
+                "SELECT last_run FROM job_runs WHERE name = ?1",
+                params![name],
+                |row| row.get::<_, i64>(0),
+            )
+            .optional()?
+            .map(from_millis))
+    }
+
+    fn save_job_run(
+        &self,
+        name: &str,
+        last_run: DateTime<Utc>,
+    ) -> std::result::Result<(), JobRunsError> {
+        self.connection().execute(
+            "INSERT INTO job_runs (name, last_run) VALUES (?1, ?2)
+            ON CONFLICT (name) DO UPDATE SET last_run = excluded.last_run",
+            params![name, to_millis(last_run)],
+        )?;
+        Ok(())
+    }
+}
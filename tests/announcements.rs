@@ -0,0 +1,156 @@
+mod fake_discord;
+
+use std::sync::Arc;
+
+use chrono::{Duration, TimeZone, Utc};
+use crabe_core::clock::MockClock;
+use crabe_de_la_crabe::storage::SqliteStorage;
+use fake_discord::{eventually, mention_count, Author, FakeDiscord};
+
+const CHANNEL_ID: u64 = 20;
+const TITLE: &str = "🦀 Did somebody say Rust? 🦀";
+
+const FERRIS: Author = Author {
+    id: 100,
+    name: "ferris",
+    bot: false,
+};
+const CRATES_BOT: Author = Author {
+    id: 101,
+    name: "crates",
+    bot: true,
+};
+
+#[tokio::test(flavor = "multi_thread")]
+async fn a_mention_beating_the_record_is_announced() {
+    let discord = FakeDiscord::start().await;
+    let start = Utc.with_ymd_and_hms(2023, 1, 2, 9, 0, 0).unwrap();
+    let clock = Arc::new(MockClock::new(start));
+    let storage = Arc::new(SqliteStorage::open(":memory:").unwrap());
+    discord.start_bot(storage.clone(), clock.clone()).await;
+
+    discord.send_message(CHANNEL_ID, &FERRIS, "I love Rust!", start);
+    eventually("the first mention", || {
+        mention_count(storage.as_ref(), &FERRIS) == 1
+    })
+    .await;
+    let later = clock.advance(Duration::days(3) + Duration::hours(2));
+    discord.send_message(CHANNEL_ID, &FERRIS, "Have you heard of Rust?", later);
+
+    let announcements = discord.wait_for_embeds(TITLE, 1).await;
+    assert_eq!(announcements[0].channel_id, CHANNEL_ID);
+    assert_eq!(
+        announcements[0].embed_description(),
+        Some(
+            "You lasted 3 days and 2 hours without mentioning Rust, that's a new record on this server!"
+        )
+    );
+    // The first mention had no record to beat.
+    assert_eq!(discord.sent_messages().len(), 1);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn mentions_ending_a_streak_at_once_announce_the_record_once() {
+    const AUTHORS: [Author; 8] = [
+        Author {
+            id: 110,
+            name: "a",
+            bot: false,
+        },
+        Author {
+            id: 111,
+            name: "b",
+            bot: false,
+        },
+        Author {
+            id: 112,
+            name: "c",
+            bot: false,
+        },
+        Author {
+            id: 113,
+            name: "d",
+            bot: false,
+        },
+        Author {
+            id: 114,
+            name: "e",
+            bot: false,
+        },
+        Author {
+            id: 115,
+            name: "f",
+            bot: false,
+        },
+        Author {
+            id: 116,
+            name: "g",
+            bot: false,
+        },
+        Author {
+            id: 117,
+            name: "h",
+            bot: false,
+        },
+    ];
+
+    let discord = FakeDiscord::start().await;
+    let start = Utc.with_ymd_and_hms(2023, 1, 2, 9, 0, 0).unwrap();
+    let clock = Arc::new(MockClock::new(start));
+    let storage = Arc::new(SqliteStorage::open(":memory:").unwrap());
+    discord.start_bot(storage.clone(), clock.clone()).await;
+
+    discord.send_message(CHANNEL_ID, &FERRIS, "I love Rust!", start);
+    eventually("the first mention", || {
+        mention_count(storage.as_ref(), &FERRIS) == 1
+    })
+    .await;
+
+    // Serenity handles every message in a task of its own, so these are tracked concurrently.
+    let later = clock.advance(Duration::days(2));
+    for author in &AUTHORS {
+        for _ in 0..3 {
+            discord.send_message(CHANNEL_ID, author, "Rust!", later);
+        }
+    }
+    eventually("the concurrent mentions", || {
+        AUTHORS
+            .iter()
+            .all(|author| mention_count(storage.as_ref(), author) == 3)
+    })
+    .await;
+
+    discord.wait_for_embeds(TITLE, 1).await;
+    // Give a second announcement the time to show up if there was one.
+    tokio::time::sleep(std::time::Duration::from_millis(300)).await;
+    assert_eq!(discord.sent_messages().len(), 1);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn mentions_by_bots_are_not_counted() {
+    let discord = FakeDiscord::start().await;
+    let start = Utc.with_ymd_and_hms(2023, 1, 2, 9, 0, 0).unwrap();
+    let clock = Arc::new(MockClock::new(start));
+    let storage = Arc::new(SqliteStorage::open(":memory:").unwrap());
+    discord.start_bot(storage.clone(), clock.clone()).await;
+
+    discord.send_message(CHANNEL_ID, &FERRIS, "I love Rust!", start);
+    eventually("the first mention", || {
+        mention_count(storage.as_ref(), &FERRIS) == 1
+    })
+    .await;
+    let bot_mention = clock.advance(Duration::days(1));
+    discord.send_message(CHANNEL_ID, &CRATES_BOT, "Rust 1.67.0 is out", bot_mention);
+    let later = clock.advance(Duration::days(1) + Duration::hours(5));
+    discord.send_message(CHANNEL_ID, &FERRIS, "Rust it is.", later);
+
+    // Had the bot ended the streak, it would have lasted a single day.
+    let announcements = discord.wait_for_embeds(TITLE, 1).await;
+    assert_eq!(
+        announcements[0].embed_description(),
+        Some(
+            "You lasted 2 days and 5 hours without mentioning Rust, that's a new record on this server!"
+        )
+    );
+    assert_eq!(mention_count(storage.as_ref(), &CRATES_BOT), 0);
+}
@@ -0,0 +1,71 @@
+use crabe_de_la_crabe::channels::Channels;
+use serenity::model::prelude::ChannelId;
+
+const CATEGORY: ChannelId = ChannelId(1);
+const CHANNEL: ChannelId = ChannelId(2);
+const THREAD: ChannelId = ChannelId(3);
+const OTHER_CHANNEL: ChannelId = ChannelId(4);
+
+/// A thread in a channel in a category.
+const THREAD_ANCESTRY: &[ChannelId] = &[THREAD, CHANNEL, CATEGORY];
+/// The channel of the thread.
+const CHANNEL_ANCESTRY: &[ChannelId] = &[CHANNEL, CATEGORY];
+/// Another channel in the same category.
+const OTHER_ANCESTRY: &[ChannelId] = &[OTHER_CHANNEL, CATEGORY];
+/// A channel without a category.
+const LONE_ANCESTRY: &[ChannelId] = &[ChannelId(5)];
+
+fn listing(include: &[ChannelId], exclude: &[ChannelId]) -> Channels {
+    Channels {
+        include: include.to_vec(),
+        exclude: exclude.to_vec(),
+    }
+}
+
+#[test]
+fn every_channel_is_allowed_without_any_entries() {
+    let channels = Channels::default();
+    assert!(channels.allows(THREAD_ANCESTRY));
+    assert!(channels.allows(LONE_ANCESTRY));
+}
+
+#[test]
+fn excluding_a_category_excludes_its_channels_and_threads() {
+    let channels = listing(&[], &[CATEGORY]);
+    assert!(!channels.allows(CHANNEL_ANCESTRY));
+    assert!(!channels.allows(THREAD_ANCESTRY));
+    assert!(channels.allows(LONE_ANCESTRY));
+}
+
+#[test]
+fn including_a_category_only_allows_its_channels_and_threads() {
+    let channels = listing(&[CATEGORY], &[]);
+    assert!(channels.allows(CHANNEL_ANCESTRY));
+    assert!(channels.allows(THREAD_ANCESTRY));
+    assert!(!channels.allows(LONE_ANCESTRY));
+}
+
+#[test]
+fn threads_inherit_from_their_channel() {
+    let channels = listing(&[], &[CHANNEL]);
+    assert!(!channels.allows(THREAD_ANCESTRY));
+    assert!(channels.allows(OTHER_ANCESTRY));
+
+    let channels = listing(&[CHANNEL], &[]);
+    assert!(channels.allows(THREAD_ANCESTRY));
+    assert!(!channels.allows(OTHER_ANCESTRY));
+}
+
+#[test]
+fn the_most_specific_entry_wins() {
+    // A channel included in an excluded category, with one of its threads excluded again.
+    let channels = listing(&[CHANNEL], &[CATEGORY, THREAD]);
+    assert!(channels.allows(CHANNEL_ANCESTRY));
+    assert!(!channels.allows(THREAD_ANCESTRY));
+    assert!(!channels.allows(OTHER_ANCESTRY));
+
+    // A thread included in an excluded channel.
+    let channels = listing(&[THREAD], &[CHANNEL]);
+    assert!(channels.allows(THREAD_ANCESTRY));
+    assert!(!channels.allows(CHANNEL_ANCESTRY));
+}
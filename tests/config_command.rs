@@ -0,0 +1,268 @@
+mod fake_discord;
+
+use std::sync::Arc;
+
+use chrono::Utc;
+use crabe_core::{
+    clock::MockClock,
+    detection::{KeywordRule, MatchMode},
+};
+use crabe_de_la_crabe::{
+    audit::AuditAction,
+    config::Config,
+    settings::{GuildSettings, Settings},
+    storage::{SqliteStorage, Storage},
+};
+use fake_discord::{Author, FakeDiscord, GUILD_ID};
+use serde_json::{json, Value};
+use serenity::model::{
+    prelude::{ChannelId, GuildId, UserId},
+    Permissions,
+};
+
+const CHANNEL_ID: u64 = 20;
+const EXCLUDED_CHANNEL_ID: u64 = 21;
+const MOD_LOG_CHANNEL_ID: u64 = 22;
+const ADMIN_ROLE_ID: u64 = 40;
+const AUDIT_TITLE: &str = "🦀 Audit Log 🦀";
+
+const FERRIS: Author = Author {
+    id: 100,
+    name: "ferris",
+    bot: false,
+};
+
+async fn start(storage: Arc<SqliteStorage>) -> FakeDiscord {
+    let discord = FakeDiscord::start().await;
+    discord
+        .start_bot(storage, Arc::new(MockClock::new(Utc::now())))
+        .await;
+    discord
+}
+
+/// The data of `/crabe config <subcommand>` with the given options and resolved channels.
+fn config(subcommand: &str, options: Value, channels: Value) -> Value {
+    json!({
+        "name": "crabe",
+        "options": [{
+            "name": "config",
+            "type": 2,
+            "options": [{ "name": subcommand, "type": 1, "options": options }],
+        }],
+        "resolved": { "channels": channels },
+    })
+}
+
+fn settings(storage: Arc<SqliteStorage>) -> GuildSettings {
+    Settings::new(storage, Config::default())
+        .load(GuildId(GUILD_ID))
+        .unwrap()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn members_without_permission_cannot_change_the_settings() {
+    let storage = Arc::new(SqliteStorage::open(":memory:").unwrap());
+    let discord = start(storage.clone()).await;
+
+    let interaction = discord.use_command(
+        CHANNEL_ID,
+        &FERRIS,
+        Permissions::SEND_MESSAGES,
+        &[],
+        config(
+            "schedule",
+            json!([{ "name": "cron", "type": 3, "value": "0 18 * * Fri" }]),
+            json!({}),
+        ),
+    );
+
+    let response = discord.wait_for_response(interaction).await;
+    assert!(response["content"]
+        .as_str()
+        .unwrap()
+        .contains("Manage Server permission"));
+    assert_eq!(settings(storage).report_schedule, "0 9 * * Mon");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn server_managers_change_the_schedule_and_excluded_channels() {
+    let storage = Arc::new(SqliteStorage::open(":memory:").unwrap());
+    let discord = start(storage.clone()).await;
+
+    let interaction = discord.use_command(
+        CHANNEL_ID,
+        &FERRIS,
+        Permissions::MANAGE_GUILD,
+        &[],
+        config(
+            "schedule",
+            json!([
+                { "name": "cron", "type": 3, "value": "0 18 * * Fri" },
+                { "name": "timezone", "type": 3, "value": "Europe/Berlin" },
+            ]),
+            json!({}),
+        ),
+    );
+    let response = discord.wait_for_response(interaction).await;
+    assert_eq!(response["content"], "Updated the report schedule.");
+    // Only the admin sees the response.
+    assert_eq!(response["flags"], 64);
+
+    let interaction = discord.use_command(
+        CHANNEL_ID,
+        &FERRIS,
+        Permissions::MANAGE_GUILD,
+        &[],
+        config(
+            "exclude-channel",
+            json!([{ "name": "channel", "type": 7, "value": EXCLUDED_CHANNEL_ID.to_string() }]),
+            json!({
+                EXCLUDED_CHANNEL_ID.to_string(): {
+                    "id": EXCLUDED_CHANNEL_ID.to_string(),
+                    "name": "off-topic",
+                    "type": 0,
+                    "permissions": "0",
+                },
+            }),
+        ),
+    );
+    let response = discord.wait_for_response(interaction).await;
+    assert_eq!(
+        response["content"],
+        format!(
+            "Mentions in <#{}> are no longer counted.",
+            EXCLUDED_CHANNEL_ID
+        )
+    );
+
+    let settings = settings(storage);
+    assert_eq!(settings.report_schedule, "0 18 * * Fri");
+    assert_eq!(settings.timezone, "Europe/Berlin");
+    assert_eq!(
+        settings.channels.exclude,
+        vec![ChannelId(EXCLUDED_CHANNEL_ID)]
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn members_of_the_admin_role_can_change_the_settings() {
+    let storage = Arc::new(SqliteStorage::open(":memory:").unwrap());
+    storage
+        .save_setting_overrides(
+            GuildId(GUILD_ID),
+            json!({ "admin_role": ADMIN_ROLE_ID }).as_object().unwrap(),
+        )
+        .unwrap();
+    let discord = start(storage.clone()).await;
+
+    let interaction = discord.use_command(
+        CHANNEL_ID,
+        &FERRIS,
+        Permissions::SEND_MESSAGES,
+        &[ADMIN_ROLE_ID],
+        config(
+            "keyword-add",
+            json!([
+                { "name": "pattern", "type": 3, "value": "ferris" },
+                { "name": "mode", "type": 3, "value": "substring" },
+            ]),
+            json!({}),
+        ),
+    );
+
+    let response = discord.wait_for_response(interaction).await;
+    assert_eq!(response["content"], "Added `ferris` to `keywords.include`.");
+    let keywords = settings(storage).keywords.include;
+    assert_eq!(
+        keywords
+            .iter()
+            .map(|k| k.pattern.as_str())
+            .collect::<Vec<_>>(),
+        ["rust", "ferris"]
+    );
+    assert_eq!(
+        keywords[1],
+        KeywordRule::new("ferris", MatchMode::Substring)
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn invalid_changes_are_rejected_without_changing_anything() {
+    let storage = Arc::new(SqliteStorage::open(":memory:").unwrap());
+    let discord = start(storage.clone()).await;
+
+    let interaction = discord.use_command(
+        CHANNEL_ID,
+        &FERRIS,
+        Permissions::MANAGE_GUILD,
+        &[],
+        config(
+            "schedule",
+            json!([
+                { "name": "cron", "type": 3, "value": "every friday" },
+                { "name": "timezone", "type": 3, "value": "Europe/Berlin" },
+            ]),
+            json!({}),
+        ),
+    );
+
+    let response = discord.wait_for_response(interaction).await;
+    assert_ne!(response["content"], "Updated the report schedule.");
+    let settings = settings(storage);
+    assert_eq!(settings.report_schedule, "0 9 * * Mon");
+    assert_eq!(settings.timezone, "UTC");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn changes_are_audited_and_mirrored_to_the_mod_log() {
+    let storage = Arc::new(SqliteStorage::open(":memory:").unwrap());
+    storage
+        .save_setting_overrides(
+            GuildId(GUILD_ID),
+            json!({ "mod_log_channel": MOD_LOG_CHANNEL_ID })
+                .as_object()
+                .unwrap(),
+        )
+        .unwrap();
+    let discord = start(storage.clone()).await;
+
+    let interaction = discord.use_command(
+        CHANNEL_ID,
+        &FERRIS,
+        Permissions::MANAGE_GUILD,
+        &[],
+        config(
+            "schedule",
+            json!([{ "name": "cron", "type": 3, "value": "0 18 * * Fri" }]),
+            json!({}),
+        ),
+    );
+    discord.wait_for_response(interaction).await;
+
+    let mirrored = discord.wait_for_embeds(AUDIT_TITLE, 1).await;
+    assert_eq!(mirrored[0].channel_id, MOD_LOG_CHANNEL_ID);
+    let description = mirrored[0].embed_description().unwrap();
+    assert!(description.contains("<@100> changed `report_schedule`"));
+    assert!(description.contains("from `\"0 9 * * Mon\"` to `\"0 18 * * Fri\"`"));
+
+    let entries = storage.load_audit_entries(GuildId(GUILD_ID), 10).unwrap();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].actor_id, UserId(FERRIS.id));
+    assert_eq!(entries[0].action, AuditAction::ConfigChange);
+    assert_eq!(entries[0].target, "report_schedule");
+    assert_eq!(entries[0].old_value, json!("0 9 * * Mon"));
+    assert_eq!(entries[0].new_value, json!("0 18 * * Fri"));
+
+    let interaction = discord.use_command(
+        CHANNEL_ID,
+        &FERRIS,
+        Permissions::MANAGE_GUILD,
+        &[],
+        json!({
+            "name": "crabe",
+            "options": [{ "name": "audit", "type": 1, "options": [] }],
+        }),
+    );
+    let response = discord.wait_for_response(interaction).await;
+    assert_eq!(response["content"], description);
+}
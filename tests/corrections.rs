@@ -0,0 +1,373 @@
+mod fake_discord;
+
+use std::{sync::Arc, time::Duration as StdDuration};
+
+use chrono::{Duration, TimeZone, Utc};
+use crabe_core::clock::{Clock, MockClock};
+use crabe_de_la_crabe::{
+    audit::AuditAction,
+    storage::{SqliteStorage, Storage},
+};
+use fake_discord::{eventually, mention_count, Author, FakeDiscord, GUILD_ID};
+use serde_json::{json, Value};
+use serenity::model::{
+    prelude::{ChannelId, GuildId},
+    Permissions,
+};
+
+const CHANNEL_ID: u64 = 20;
+const CAR_CHANNEL_ID: u64 = 21;
+const REPORT_CHANNEL_ID: u64 = 30;
+
+const FERRIS: Author = Author {
+    id: 100,
+    name: "ferris",
+    bot: false,
+};
+const CORRO: Author = Author {
+    id: 102,
+    name: "corro",
+    bot: false,
+};
+
+const DAY: u64 = 24 * 60 * 60;
+
+/// The data of `/crabe record clear`, clearing the record of the server.
+fn clear_record() -> Value {
+    crabe(
+        json!({
+            "name": "record",
+            "type": 2,
+            "options": [{ "name": "clear", "type": 1, "options": [] }],
+        }),
+        json!({}),
+    )
+}
+
+/// The data of `/crabe` with a single subcommand, or a group holding one, and resolved values.
+fn crabe(options: Value, resolved: Value) -> Value {
+    json!({ "name": "crabe", "options": [options], "resolved": resolved })
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn disqualifying_a_message_recomputes_the_counts_and_records() {
+    let discord = FakeDiscord::start().await;
+    let start = Utc.with_ymd_and_hms(2023, 1, 2, 9, 0, 0).unwrap();
+    let clock = Arc::new(MockClock::new(start + Duration::days(10)));
+    let storage = Arc::new(SqliteStorage::open(":memory:").unwrap());
+    discord.start_bot(storage.clone(), clock).await;
+
+    discord.send_message(CHANNEL_ID, &FERRIS, "Rust!", start);
+    eventually("the first mention", || {
+        mention_count(storage.as_ref(), &FERRIS) == 1
+    })
+    .await;
+    let misfire = discord.send_message(
+        CAR_CHANNEL_ID,
+        &CORRO,
+        "There is rust all over my car.",
+        start + Duration::days(1),
+    );
+    eventually("the misfire", || {
+        mention_count(storage.as_ref(), &CORRO) == 1
+    })
+    .await;
+    discord.send_message(CHANNEL_ID, &FERRIS, "More Rust!", start + Duration::days(5));
+    eventually("the third mention", || {
+        mention_count(storage.as_ref(), &FERRIS) == 2
+    })
+    .await;
+
+    let records = storage.load_records().unwrap();
+    assert_eq!(
+        records[&GuildId(GUILD_ID)].duration,
+        Some(StdDuration::from_secs(4 * DAY))
+    );
+
+    let link = format!(
+        "https://discord.com/channels/{}/{}/{}",
+        GUILD_ID, CAR_CHANNEL_ID, misfire
+    );
+    let interaction = discord.use_command(
+        CHANNEL_ID,
+        &FERRIS,
+        Permissions::MANAGE_GUILD,
+        &[],
+        crabe(
+            json!({
+                "name": "disqualify",
+                "type": 1,
+                "options": [{ "name": "message", "type": 3, "value": link }],
+            }),
+            json!({}),
+        ),
+    );
+    let response = discord.wait_for_response(interaction).await;
+    // Rebuilding can take a while, so the response is deferred first.
+    assert!(discord.was_deferred(interaction));
+    assert_eq!(
+        response["content"],
+        "Disqualified the message, taking back 1 mention."
+    );
+    assert_eq!(response["flags"], 64);
+
+    // The streaks before and after the misfire are joined into a new record.
+    assert_eq!(mention_count(storage.as_ref(), &CORRO), 0);
+    assert_eq!(mention_count(storage.as_ref(), &FERRIS), 2);
+    let record = storage.load_records().unwrap()[&GuildId(GUILD_ID)].clone();
+    assert_eq!(record.duration, Some(StdDuration::from_secs(5 * DAY)));
+    assert_eq!(record.last_mention, Some(start + Duration::days(5)));
+    let channel_records = storage.load_channel_records().unwrap();
+    assert_eq!(
+        channel_records[&GuildId(GUILD_ID)][&ChannelId(CAR_CHANNEL_ID)],
+        Default::default()
+    );
+
+    let entries = storage.load_audit_entries(GuildId(GUILD_ID), 10).unwrap();
+    assert_eq!(entries[0].action, AuditAction::Disqualification);
+    assert_eq!(entries[0].target, misfire.to_string());
+    assert!(entries[0].describe().contains(&link));
+
+    // A message that no longer counts can't be disqualified twice.
+    let interaction = discord.use_command(
+        CHANNEL_ID,
+        &FERRIS,
+        Permissions::MANAGE_GUILD,
+        &[],
+        crabe(
+            json!({
+                "name": "disqualify",
+                "type": 1,
+                "options": [{ "name": "message", "type": 3, "value": misfire.to_string() }],
+            }),
+            json!({}),
+        ),
+    );
+    let response = discord.wait_for_response(interaction).await;
+    assert_eq!(
+        response["content"],
+        "No mentions of Rust were counted for that message."
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn admins_correct_counts_and_records() {
+    let discord = FakeDiscord::start().await;
+    let start = Utc.with_ymd_and_hms(2023, 1, 2, 9, 0, 0).unwrap();
+    let clock = Arc::new(MockClock::new(start));
+    let storage = Arc::new(SqliteStorage::open(":memory:").unwrap());
+    discord.start_bot(storage.clone(), clock).await;
+
+    discord.send_message(CHANNEL_ID, &CORRO, "Rust!", start);
+    eventually("the mention", || {
+        mention_count(storage.as_ref(), &CORRO) == 1
+    })
+    .await;
+
+    let reset = crabe(
+        json!({
+            "name": "count",
+            "type": 1,
+            "options": [{ "name": "user", "type": 6, "value": CORRO.id.to_string() }],
+        }),
+        json!({
+            "users": {
+                CORRO.id.to_string(): {
+                    "id": CORRO.id.to_string(),
+                    "username": CORRO.name,
+                    "discriminator": "0001",
+                    "avatar": null,
+                },
+            },
+        }),
+    );
+    let interaction =
+        discord.use_command(CHANNEL_ID, &CORRO, Permissions::empty(), &[], reset.clone());
+    let response = discord.wait_for_response(interaction).await;
+    assert!(response["content"]
+        .as_str()
+        .unwrap()
+        .contains("Manage Server permission"));
+    assert_eq!(mention_count(storage.as_ref(), &CORRO), 1);
+
+    let interaction =
+        discord.use_command(CHANNEL_ID, &FERRIS, Permissions::MANAGE_GUILD, &[], reset);
+    let response = discord.wait_for_response(interaction).await;
+    assert_eq!(
+        response["content"],
+        "The mention count of <@102> is now 0 instead of 1."
+    );
+    assert_eq!(mention_count(storage.as_ref(), &CORRO), 0);
+
+    let interaction = discord.use_command(
+        CHANNEL_ID,
+        &FERRIS,
+        Permissions::MANAGE_GUILD,
+        &[],
+        crabe(
+            json!({
+                "name": "record",
+                "type": 2,
+                "options": [{
+                    "name": "set",
+                    "type": 1,
+                    "options": [
+                        { "name": "days", "type": 4, "value": 3 },
+                        { "name": "hours", "type": 4, "value": 12 },
+                    ],
+                }],
+            }),
+            json!({}),
+        ),
+    );
+    let response = discord.wait_for_response(interaction).await;
+    assert_eq!(
+        response["content"],
+        "The record of this server is now 3 days and 12 hours."
+    );
+    let record = storage.load_records().unwrap()[&GuildId(GUILD_ID)].clone();
+    assert_eq!(
+        record.duration,
+        Some(StdDuration::from_secs(3 * DAY + 12 * 3600))
+    );
+    // The ongoing streak is kept.
+    assert_eq!(record.last_mention, Some(start));
+
+    let interaction = discord.use_command(
+        CHANNEL_ID,
+        &FERRIS,
+        Permissions::MANAGE_GUILD,
+        &[],
+        clear_record(),
+    );
+    let response = discord.wait_for_response(interaction).await;
+    assert_eq!(response["content"], "Cleared the record of this server.");
+    let record = storage.load_records().unwrap()[&GuildId(GUILD_ID)].clone();
+    assert_eq!(record.duration, None);
+    assert_eq!(record.last_mention, Some(start));
+
+    let actions = storage
+        .load_audit_entries(GuildId(GUILD_ID), 10)
+        .unwrap()
+        .iter()
+        .map(|entry| entry.action)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        actions,
+        [
+            AuditAction::RecordChange,
+            AuditAction::RecordChange,
+            AuditAction::CountChange
+        ]
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn rebuilding_disqualifies_the_mentions_of_a_rule() {
+    let discord = FakeDiscord::start().await;
+    let start = Utc.with_ymd_and_hms(2023, 1, 2, 9, 0, 0).unwrap();
+    let clock = Arc::new(MockClock::new(start + Duration::days(10)));
+    let storage = Arc::new(SqliteStorage::open(":memory:").unwrap());
+    discord.start_bot(storage.clone(), clock).await;
+
+    discord.send_message(CHANNEL_ID, &FERRIS, "Rust!", start);
+    discord.send_message(CHANNEL_ID, &CORRO, "🦀", start + Duration::days(2));
+    eventually("both mentions", || {
+        mention_count(storage.as_ref(), &FERRIS) == 1
+            && mention_count(storage.as_ref(), &CORRO) == 1
+    })
+    .await;
+
+    let interaction = discord.use_command(
+        CHANNEL_ID,
+        &FERRIS,
+        Permissions::MANAGE_GUILD,
+        &[],
+        crabe(
+            json!({
+                "name": "rebuild",
+                "type": 1,
+                "options": [{ "name": "rule", "type": 3, "value": "rust" }],
+            }),
+            json!({}),
+        ),
+    );
+    let response = discord.wait_for_response(interaction).await;
+    assert!(discord.was_deferred(interaction));
+    assert_eq!(
+        response["content"],
+        "Disqualified 1 mention matching `rust` and rebuilt the counts and records."
+    );
+
+    // Only the mention matched by the rule is gone, the emoji still counts.
+    assert_eq!(mention_count(storage.as_ref(), &FERRIS), 0);
+    assert_eq!(mention_count(storage.as_ref(), &CORRO), 1);
+    let record = storage.load_records().unwrap()[&GuildId(GUILD_ID)].clone();
+    assert_eq!(record.duration, Some(StdDuration::ZERO));
+    assert_eq!(record.last_mention, Some(start + Duration::days(2)));
+    let mentions = storage.load_mentions(GuildId(GUILD_ID)).unwrap();
+    assert_eq!(mentions.len(), 1);
+    assert_eq!(mentions[0].rule.as_deref(), Some("🦀"));
+
+    let entries = storage.load_audit_entries(GuildId(GUILD_ID), 10).unwrap();
+    assert_eq!(entries[0].action, AuditAction::Rebuild);
+    assert_eq!(entries[0].target, "rust");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn the_ongoing_streak_beats_a_cleared_record() {
+    const MILESTONE: &str = "🦀 A new record is in the making! 🦀";
+    const RECORD: &str = "🦀 Did somebody say Rust? 🦀";
+
+    let discord = FakeDiscord::start().await;
+    let start = Utc.with_ymd_and_hms(2023, 1, 2, 9, 0, 0).unwrap();
+    let clock = Arc::new(MockClock::new(start + Duration::days(1)));
+    let storage = Arc::new(SqliteStorage::open(":memory:").unwrap());
+    let overrides = json!({ "report_channel": REPORT_CHANNEL_ID });
+    storage
+        .save_setting_overrides(GuildId(GUILD_ID), overrides.as_object().unwrap())
+        .unwrap();
+    discord.start_bot(storage.clone(), clock.clone()).await;
+
+    discord.send_message(CHANNEL_ID, &FERRIS, "Rust!", start);
+    eventually("the first mention", || {
+        mention_count(storage.as_ref(), &FERRIS) == 1
+    })
+    .await;
+    discord.send_message(CHANNEL_ID, &FERRIS, "Rust!", start + Duration::days(1));
+    discord.wait_for_embeds(RECORD, 1).await;
+
+    let interaction = discord.use_command(
+        CHANNEL_ID,
+        &FERRIS,
+        Permissions::MANAGE_GUILD,
+        &[],
+        clear_record(),
+    );
+    discord.wait_for_response(interaction).await;
+    assert_eq!(
+        storage.load_records().unwrap()[&GuildId(GUILD_ID)].duration,
+        None
+    );
+
+    // Without a record to beat, the ongoing streak is a new record in the making right away.
+    clock.advance(Duration::hours(2));
+    let milestones = discord.wait_for_embeds(MILESTONE, 1).await;
+    assert_eq!(milestones[0].channel_id, REPORT_CHANNEL_ID);
+    assert_eq!(
+        milestones[0].embed_description(),
+        Some(
+            "Nobody has mentioned Rust for 2 hours, the longest since the record was cleared. Keep going!"
+        )
+    );
+
+    // The streak then becomes the new record once it ends.
+    discord.send_message(CHANNEL_ID, &CORRO, "Rust!", clock.now());
+    let records = discord.wait_for_embeds(RECORD, 2).await;
+    assert_eq!(
+        records[1].embed_description(),
+        Some("You lasted 2 hours without mentioning Rust, that's a new record on this server!")
+    );
+    let record = storage.load_records().unwrap()[&GuildId(GUILD_ID)].clone();
+    assert_eq!(record.duration, Some(StdDuration::from_secs(2 * 60 * 60)));
+}
@@ -0,0 +1,167 @@
+mod fake_discord;
+
+use std::{sync::Arc, time::Duration as StdDuration};
+
+use chrono::{Duration, TimeZone, Utc};
+use crabe_core::clock::MockClock;
+use crabe_de_la_crabe::storage::{SqliteStorage, Storage};
+use fake_discord::{eventually, mention_count, Author, FakeDiscord, GUILD_ID};
+use serenity::model::prelude::GuildId;
+
+const CHANNEL_ID: u64 = 20;
+
+const FERRIS: Author = Author {
+    id: 100,
+    name: "ferris",
+    bot: false,
+};
+const CORRO: Author = Author {
+    id: 102,
+    name: "corro",
+    bot: false,
+};
+
+#[tokio::test(flavor = "multi_thread")]
+async fn an_edit_adding_a_mention_counts_it() {
+    let discord = FakeDiscord::start().await;
+    let start = Utc.with_ymd_and_hms(2023, 1, 2, 9, 0, 0).unwrap();
+    let clock = Arc::new(MockClock::new(start));
+    let storage = Arc::new(SqliteStorage::open(":memory:").unwrap());
+    discord.start_bot(storage.clone(), clock.clone()).await;
+
+    let message_id = discord.send_message(CHANNEL_ID, &FERRIS, "Hello!", start);
+    let edited_at = clock.advance(Duration::hours(1));
+    discord.edit_message(message_id, CHANNEL_ID, &FERRIS, "Hello, Rust!", edited_at);
+    eventually("the edited mention", || {
+        mention_count(storage.as_ref(), &FERRIS) == 1
+    })
+    .await;
+
+    let mentions = storage.load_mentions(GuildId(GUILD_ID)).unwrap();
+    assert_eq!(mentions.len(), 1);
+    assert_eq!(mentions[0].sent_at, edited_at);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn an_edit_removing_a_mention_keeps_it() {
+    let discord = FakeDiscord::start().await;
+    let start = Utc.with_ymd_and_hms(2023, 1, 2, 9, 0, 0).unwrap();
+    let clock = Arc::new(MockClock::new(start));
+    let storage = Arc::new(SqliteStorage::open(":memory:").unwrap());
+    discord.start_bot(storage.clone(), clock.clone()).await;
+
+    let message_id = discord.send_message(CHANNEL_ID, &FERRIS, "Rust!", start);
+    eventually("the mention", || {
+        mention_count(storage.as_ref(), &FERRIS) == 1
+    })
+    .await;
+
+    let edited_at = clock.advance(Duration::minutes(5));
+    discord.edit_message(message_id, CHANNEL_ID, &FERRIS, "Nothing.", edited_at);
+    discord.send_message(CHANNEL_ID, &CORRO, "Rust?", edited_at);
+    eventually("the later mention", || {
+        mention_count(storage.as_ref(), &CORRO) == 1
+    })
+    .await;
+
+    assert_eq!(mention_count(storage.as_ref(), &FERRIS), 1);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn deleting_a_message_within_the_grace_window_reverts_its_mentions() {
+    let discord = FakeDiscord::start().await;
+    let start = Utc.with_ymd_and_hms(2023, 1, 2, 9, 0, 0).unwrap();
+    let clock = Arc::new(MockClock::new(start));
+    let storage = Arc::new(SqliteStorage::open(":memory:").unwrap());
+    discord.start_bot(storage.clone(), clock.clone()).await;
+
+    discord.send_message(CHANNEL_ID, &CORRO, "Rust!", start);
+    eventually("the first mention", || {
+        mention_count(storage.as_ref(), &CORRO) == 1
+    })
+    .await;
+
+    let sent_at = clock.advance(Duration::days(1));
+    let message_id = discord.send_message(CHANNEL_ID, &FERRIS, "Rust!", sent_at);
+    eventually("the second mention", || {
+        mention_count(storage.as_ref(), &FERRIS) == 1
+    })
+    .await;
+    let record = storage.load_records().unwrap()[&GuildId(GUILD_ID)].clone();
+    assert_eq!(record.duration, Some(StdDuration::from_secs(24 * 60 * 60)));
+
+    clock.advance(Duration::seconds(10));
+    discord.delete_message(message_id, CHANNEL_ID);
+    eventually("the reverted mention", || {
+        mention_count(storage.as_ref(), &FERRIS) == 0
+    })
+    .await;
+
+    // The streak the deleted message ended goes on as if it was never sent.
+    let record = storage.load_records().unwrap()[&GuildId(GUILD_ID)].clone();
+    assert_eq!(record.duration, Some(StdDuration::ZERO));
+    assert_eq!(record.last_mention, Some(start));
+    assert_eq!(storage.load_mentions(GuildId(GUILD_ID)).unwrap().len(), 1);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn deleting_a_message_after_the_grace_window_keeps_its_mentions() {
+    let discord = FakeDiscord::start().await;
+    let start = Utc.with_ymd_and_hms(2023, 1, 2, 9, 0, 0).unwrap();
+    let clock = Arc::new(MockClock::new(start));
+    let storage = Arc::new(SqliteStorage::open(":memory:").unwrap());
+    discord.start_bot(storage.clone(), clock.clone()).await;
+
+    let message_id = discord.send_message(CHANNEL_ID, &FERRIS, "Rust!", start);
+    eventually("the mention", || {
+        mention_count(storage.as_ref(), &FERRIS) == 1
+    })
+    .await;
+
+    let deleted_at = clock.advance(Duration::minutes(1));
+    discord.delete_message(message_id, CHANNEL_ID);
+    discord.send_message(CHANNEL_ID, &CORRO, "Rust?", deleted_at);
+    eventually("the later mention", || {
+        mention_count(storage.as_ref(), &CORRO) == 1
+    })
+    .await;
+
+    assert_eq!(mention_count(storage.as_ref(), &FERRIS), 1);
+    assert_eq!(storage.load_mentions(GuildId(GUILD_ID)).unwrap().len(), 2);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn a_bulk_deletion_within_the_grace_window_reverts_every_message() {
+    let discord = FakeDiscord::start().await;
+    let start = Utc.with_ymd_and_hms(2023, 1, 2, 9, 0, 0).unwrap();
+    let clock = Arc::new(MockClock::new(start));
+    let storage = Arc::new(SqliteStorage::open(":memory:").unwrap());
+    discord.start_bot(storage.clone(), clock.clone()).await;
+
+    discord.send_message(CHANNEL_ID, &CORRO, "Rust!", start);
+    eventually("the first mention", || {
+        mention_count(storage.as_ref(), &CORRO) == 1
+    })
+    .await;
+
+    let sent_at = clock.advance(Duration::days(1));
+    let first = discord.send_message(CHANNEL_ID, &FERRIS, "Rust!", sent_at);
+    let second = discord.send_message(CHANNEL_ID, &CORRO, "Rust?", sent_at);
+    eventually("the spam", || {
+        mention_count(storage.as_ref(), &FERRIS) == 1
+            && mention_count(storage.as_ref(), &CORRO) == 2
+    })
+    .await;
+
+    clock.advance(Duration::seconds(10));
+    discord.delete_messages(&[first, second], CHANNEL_ID);
+    eventually("the reverted mentions", || {
+        mention_count(storage.as_ref(), &FERRIS) == 0
+            && mention_count(storage.as_ref(), &CORRO) == 1
+    })
+    .await;
+
+    let record = storage.load_records().unwrap()[&GuildId(GUILD_ID)].clone();
+    assert_eq!(record.duration, Some(StdDuration::ZERO));
+    assert_eq!(record.last_mention, Some(start));
+}
@@ -0,0 +1,683 @@
+//! A fake Discord serving both the REST API and the gateway on localhost, so the bot can be
+//! tested end to end without a token.
+//!
+//! Events are scripted through [`FakeDiscord::dispatch`] and only dispatched once the bot has
+//! identified, while every message the bot sends is recorded to be checked afterwards.
+
+// Every test file includes this module but only uses some of it.
+#![allow(dead_code)]
+
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use async_tungstenite::tungstenite::Message as WsMessage;
+use chrono::{DateTime, Utc};
+use crabe_core::clock::Clock;
+use crabe_de_la_crabe::{storage::Storage, Bot};
+use futures::{SinkExt, StreamExt};
+use hyper::{
+    service::{make_service_fn, service_fn},
+    Body, Method, Request, Response, Server, StatusCode,
+};
+use serde_json::{json, Value};
+use serenity::model::{
+    prelude::{GuildId, UserId},
+    Permissions,
+};
+use tokio::{
+    net::{TcpListener, TcpStream},
+    sync::mpsc,
+};
+
+/// The ID of the bot itself, which is also the ID of its application.
+pub const BOT_ID: u64 = 1;
+/// The ID of the guild the scripted events happen in.
+pub const GUILD_ID: u64 = 10;
+/// The ID of another guild the bot is on, which is left alone unless a test says otherwise.
+pub const OTHER_GUILD_ID: u64 = 11;
+
+/// The types of interaction responses sending a message right away or deferring it.
+const CHANNEL_MESSAGE: u64 = 4;
+const DEFERRED_CHANNEL_MESSAGE: u64 = 5;
+
+/// How long to wait for the bot to send a message before giving up.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A user sending messages in the scripted events.
+pub struct Author {
+    pub id: u64,
+    pub name: &'static str,
+    pub bot: bool,
+}
+
+/// A message the bot sent through the REST API.
+#[derive(Clone, Debug)]
+pub struct SentMessage {
+    pub channel_id: u64,
+    pub body: Value,
+}
+
+impl SentMessage {
+    pub fn embed_title(&self) -> Option<&str> {
+        self.body["embeds"][0]["title"].as_str()
+    }
+
+    pub fn embed_description(&self) -> Option<&str> {
+        self.body["embeds"][0]["description"].as_str()
+    }
+}
+
+/// What the REST API and the gateway share.
+struct Shared {
+    gateway_url: String,
+    next_id: AtomicU64,
+    users: Mutex<HashMap<u64, Value>>,
+    sent: Mutex<Vec<SentMessage>>,
+    /// The responses of the bot to interactions, by the ID of the interaction. A deferred response
+    /// only shows up here once the bot edited it.
+    responses: Mutex<HashMap<u64, Value>>,
+    /// The data of the deferred responses to interactions, by the ID of the interaction.
+    deferred: Mutex<HashMap<u64, Value>>,
+    /// The scripted events, taken by the gateway session of the bot once it identified.
+    events: tokio::sync::Mutex<mpsc::UnboundedReceiver<(&'static str, Value)>>,
+}
+
+pub struct FakeDiscord {
+    api_url: String,
+    shared: Arc<Shared>,
+    events: mpsc::UnboundedSender<(&'static str, Value)>,
+}
+
+impl FakeDiscord {
+    /// Starts serving the REST API and the gateway on random ports.
+    pub async fn start() -> Self {
+        let gateway = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let (events, receiver) = mpsc::unbounded_channel();
+        let shared = Arc::new(Shared {
+            gateway_url: format!("ws://{}", gateway.local_addr().unwrap()),
+            next_id: AtomicU64::new(1000),
+            users: Mutex::new(HashMap::new()),
+            sent: Mutex::new(Vec::new()),
+            responses: Mutex::new(HashMap::new()),
+            deferred: Mutex::new(HashMap::new()),
+            events: tokio::sync::Mutex::new(receiver),
+        });
+
+        let gateway_shared = shared.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = gateway.accept().await {
+                tokio::spawn(serve_gateway(stream, gateway_shared.clone()));
+            }
+        });
+
+        let rest_shared = shared.clone();
+        let server = Server::bind(&SocketAddr::from(([127, 0, 0, 1], 0))).serve(make_service_fn(
+            move |_| {
+                let shared = rest_shared.clone();
+                async move {
+                    Ok::<_, Infallible>(service_fn(move |request| {
+                        serve_rest(request, shared.clone())
+                    }))
+                }
+            },
+        ));
+        let api_url = format!("http://{}", server.local_addr());
+        tokio::spawn(server);
+
+        Self {
+            api_url,
+            shared,
+            events,
+        }
+    }
+
+    /// Starts the bot against the fake Discord, ticking its scheduler often to keep tests fast.
+    pub async fn start_bot(&self, storage: Arc<dyn Storage>, clock: Arc<dyn Clock>) {
+        // The logs of the bot are only shown for failing tests.
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+        let mut client = Bot::new(storage)
+            .with_clock(clock)
+            .with_api_url(&self.api_url)
+            .with_tick_interval(Duration::from_millis(50))
+            .client("fake")
+            .await
+            .unwrap();
+        tokio::spawn(async move { client.start().await });
+    }
+
+    /// Dispatches an event such as `MESSAGE_CREATE` to the bot.
+    pub fn dispatch(&self, event: &'static str, data: Value) {
+        self.events.send((event, data)).unwrap();
+    }
+
+    /// Dispatches a message sent by `author` in a channel of the guild at `sent_at`, returning
+    /// its ID.
+    pub fn send_message(
+        &self,
+        channel_id: u64,
+        author: &Author,
+        content: &str,
+        sent_at: DateTime<Utc>,
+    ) -> u64 {
+        self.send_message_with(channel_id, author, content, sent_at, json!({}))
+    }
+
+    /// Dispatches a message like [`FakeDiscord::send_message`], with the given fields, such as its
+    /// stickers or the webhook that sent it, replacing those of a plain message.
+    pub fn send_message_with(
+        &self,
+        channel_id: u64,
+        author: &Author,
+        content: &str,
+        sent_at: DateTime<Utc>,
+        fields: Value,
+    ) -> u64 {
+        let id = self.shared.next_id.fetch_add(1, Ordering::SeqCst);
+        let mut message = message(id, channel_id, self.register(author), content, sent_at);
+        for (key, value) in fields.as_object().expect("The fields are an object.") {
+            message[key] = value.clone();
+        }
+
+        self.dispatch("MESSAGE_CREATE", message);
+        id
+    }
+
+    /// Dispatches a reaction by `author`, a member with the given roles, to a message.
+    ///
+    /// The emoji is either `{ "id": null, "name": "🦀" }` or the ID and name of a custom one.
+    pub fn react(
+        &self,
+        channel_id: u64,
+        message_id: u64,
+        author: &Author,
+        roles: &[u64],
+        emoji: Value,
+    ) {
+        let user = self.register(author);
+        self.dispatch(
+            "MESSAGE_REACTION_ADD",
+            json!({
+                "user_id": author.id.to_string(),
+                "channel_id": channel_id.to_string(),
+                "message_id": message_id.to_string(),
+                "guild_id": GUILD_ID.to_string(),
+                "member": {
+                    "user": user,
+                    "roles": roles.iter().map(u64::to_string).collect::<Vec<_>>(),
+                    "joined_at": "2021-01-01T00:00:00+00:00",
+                    "deaf": false,
+                    "mute": false,
+                },
+                "emoji": emoji,
+            }),
+        );
+    }
+
+    /// Makes an author known to the REST API, returning them as a user.
+    fn register(&self, author: &Author) -> Value {
+        let user = user(author.id, author.name, author.bot);
+        self.shared
+            .users
+            .lock()
+            .unwrap()
+            .insert(author.id, user.clone());
+        user
+    }
+
+    /// Dispatches an edit of a message by `author` replacing its content at `edited_at`.
+    pub fn edit_message(
+        &self,
+        message_id: u64,
+        channel_id: u64,
+        author: &Author,
+        content: &str,
+        edited_at: DateTime<Utc>,
+    ) {
+        self.dispatch(
+            "MESSAGE_UPDATE",
+            json!({
+                "id": message_id.to_string(),
+                "channel_id": channel_id.to_string(),
+                "guild_id": GUILD_ID.to_string(),
+                "author": user(author.id, author.name, author.bot),
+                "content": content,
+                "edited_timestamp": edited_at.to_rfc3339(),
+            }),
+        );
+    }
+
+    /// Dispatches the deletion of a message.
+    pub fn delete_message(&self, message_id: u64, channel_id: u64) {
+        self.dispatch(
+            "MESSAGE_DELETE",
+            json!({
+                "id": message_id.to_string(),
+                "channel_id": channel_id.to_string(),
+                "guild_id": GUILD_ID.to_string(),
+            }),
+        );
+    }
+
+    /// Dispatches the deletion of several messages at once, e.g. by a moderator purging a channel.
+    pub fn delete_messages(&self, message_ids: &[u64], channel_id: u64) {
+        self.dispatch(
+            "MESSAGE_DELETE_BULK",
+            json!({
+                "ids": message_ids.iter().map(u64::to_string).collect::<Vec<_>>(),
+                "channel_id": channel_id.to_string(),
+                "guild_id": GUILD_ID.to_string(),
+            }),
+        );
+    }
+
+    /// Dispatches a slash command used in a channel of the guild by `author`, a member with the
+    /// given permissions and roles, returning the ID of the interaction.
+    ///
+    /// The data holds the name of the command, its options and the channels they resolve to.
+    pub fn use_command(
+        &self,
+        channel_id: u64,
+        author: &Author,
+        permissions: Permissions,
+        roles: &[u64],
+        data: Value,
+    ) -> u64 {
+        self.use_command_in(GUILD_ID, channel_id, author, permissions, roles, data)
+    }
+
+    /// Dispatches a slash command like [`FakeDiscord::use_command`], but in the given guild.
+    pub fn use_command_in(
+        &self,
+        guild_id: u64,
+        channel_id: u64,
+        author: &Author,
+        permissions: Permissions,
+        roles: &[u64],
+        data: Value,
+    ) -> u64 {
+        let id = self.shared.next_id.fetch_add(1, Ordering::SeqCst);
+        let mut data = data;
+        data["id"] = json!(BOT_ID.to_string());
+        data["type"] = json!(1);
+
+        self.dispatch(
+            "INTERACTION_CREATE",
+            json!({
+                "id": id.to_string(),
+                "application_id": BOT_ID.to_string(),
+                "type": 2,
+                "data": data,
+                "guild_id": guild_id.to_string(),
+                "channel_id": channel_id.to_string(),
+                "member": {
+                    "user": user(author.id, author.name, author.bot),
+                    "roles": roles.iter().map(u64::to_string).collect::<Vec<_>>(),
+                    "joined_at": "2021-01-01T00:00:00+00:00",
+                    "deaf": false,
+                    "mute": false,
+                    "permissions": permissions.bits().to_string(),
+                },
+                "token": format!("token-{}", id),
+                "version": 1,
+                "locale": "en-US",
+            }),
+        );
+        id
+    }
+
+    /// Dispatches a click by `author` on a button with the given custom ID, attached to a message
+    /// of the bot, returning the ID of the interaction.
+    pub fn press_button(&self, channel_id: u64, author: &Author, custom_id: &str) -> u64 {
+        let id = self.shared.next_id.fetch_add(1, Ordering::SeqCst);
+        let message_id = self.shared.next_id.fetch_add(1, Ordering::SeqCst);
+
+        self.dispatch(
+            "INTERACTION_CREATE",
+            json!({
+                "id": id.to_string(),
+                "application_id": BOT_ID.to_string(),
+                "type": 3,
+                "data": { "custom_id": custom_id, "component_type": 2 },
+                "guild_id": GUILD_ID.to_string(),
+                "channel_id": channel_id.to_string(),
+                "member": {
+                    "user": user(author.id, author.name, author.bot),
+                    "roles": [],
+                    "joined_at": "2021-01-01T00:00:00+00:00",
+                    "deaf": false,
+                    "mute": false,
+                    "permissions": Permissions::SEND_MESSAGES.bits().to_string(),
+                },
+                "message": message(
+                    message_id,
+                    channel_id,
+                    user(BOT_ID, "Crabe", true),
+                    "",
+                    Utc::now(),
+                ),
+                "token": format!("token-{}", id),
+                "version": 1,
+                "locale": "en-US",
+            }),
+        );
+        id
+    }
+
+    /// Waits for the bot to respond to an interaction, returning the data of its response.
+    pub async fn wait_for_response(&self, interaction_id: u64) -> Value {
+        let waiting = async {
+            loop {
+                if let Some(response) = self.shared.responses.lock().unwrap().get(&interaction_id) {
+                    return response["data"].clone();
+                }
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+        };
+
+        tokio::time::timeout(TIMEOUT, waiting)
+            .await
+            .unwrap_or_else(|_| panic!("The bot never responded to {}.", interaction_id))
+    }
+
+    /// Whether the bot deferred its response to an interaction before responding.
+    pub fn was_deferred(&self, interaction_id: u64) -> bool {
+        self.shared
+            .deferred
+            .lock()
+            .unwrap()
+            .contains_key(&interaction_id)
+    }
+
+    /// The messages the bot sent so far.
+    pub fn sent_messages(&self) -> Vec<SentMessage> {
+        self.shared.sent.lock().unwrap().clone()
+    }
+
+    /// Waits for the bot to send `count` messages with an embed of the given title.
+    pub async fn wait_for_embeds(&self, title: &str, count: usize) -> Vec<SentMessage> {
+        let waiting = async {
+            loop {
+                let messages = self
+                    .sent_messages()
+                    .into_iter()
+                    .filter(|message| message.embed_title() == Some(title))
+                    .collect::<Vec<_>>();
+                if messages.len() >= count {
+                    return messages;
+                }
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+        };
+
+        tokio::time::timeout(TIMEOUT, waiting)
+            .await
+            .unwrap_or_else(|_| {
+                panic!(
+                    "The bot never sent {} embeds titled \"{}\", only {:#?}.",
+                    count,
+                    title,
+                    self.sent_messages()
+                )
+            })
+    }
+}
+
+/// Waits for a condition to hold, such as the bot having handled an event.
+///
+/// Serenity handles every event in a task of its own, so events the bot should handle in order
+/// have to be dispatched one after the other.
+pub async fn eventually(what: &str, condition: impl Fn() -> bool) {
+    let waiting = async {
+        while !condition() {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    };
+
+    if tokio::time::timeout(TIMEOUT, waiting).await.is_err() {
+        panic!("Timed out waiting for {}.", what);
+    }
+}
+
+/// The number of mentions the bot saved for an author so far.
+pub fn mention_count(storage: &dyn Storage, author: &Author) -> usize {
+    mention_count_in(storage, GUILD_ID, author)
+}
+
+/// The number of mentions the bot saved for an author in the given guild so far.
+pub fn mention_count_in(storage: &dyn Storage, guild_id: u64, author: &Author) -> usize {
+    storage
+        .load_mention_counts()
+        .unwrap()
+        .get(&GuildId(guild_id))
+        .and_then(|counts| counts.get(&UserId(author.id)).copied())
+        .unwrap_or_default()
+}
+
+fn user(id: u64, name: &str, bot: bool) -> Value {
+    json!({
+        "id": id.to_string(),
+        "username": name,
+        "discriminator": "0001",
+        "avatar": null,
+        "bot": bot,
+    })
+}
+
+fn message(
+    id: u64,
+    channel_id: u64,
+    author: Value,
+    content: &str,
+    sent_at: DateTime<Utc>,
+) -> Value {
+    json!({
+        "id": id.to_string(),
+        "channel_id": channel_id.to_string(),
+        "guild_id": GUILD_ID.to_string(),
+        "author": author,
+        "content": content,
+        "timestamp": sent_at.to_rfc3339(),
+        "edited_timestamp": null,
+        "tts": false,
+        "mention_everyone": false,
+        "mentions": [],
+        "mention_roles": [],
+        "attachments": [],
+        "embeds": [],
+        "pinned": false,
+        "type": 0,
+    })
+}
+
+/// Plays a gateway session: greets the bot, answers its heartbeats, and dispatches `READY`
+/// followed by the scripted events once it identified.
+async fn serve_gateway(stream: TcpStream, shared: Arc<Shared>) {
+    let mut socket = match async_tungstenite::tokio::accept_async(stream).await {
+        Ok(socket) => socket,
+        Err(_) => return,
+    };
+    let send = |payload: Value| WsMessage::Text(payload.to_string());
+
+    let hello = json!({ "op": 10, "d": { "heartbeat_interval": 45000 } });
+    if socket.send(send(hello)).await.is_err() {
+        return;
+    }
+
+    // Heartbeats may arrive before the bot identifies.
+    loop {
+        let op = match socket.next().await {
+            Some(Ok(WsMessage::Text(text))) => opcode(&text),
+            Some(Ok(_)) => continue,
+            _ => return,
+        };
+        let reply = match op {
+            Some(1) => json!({ "op": 11 }),
+            Some(2) => break,
+            _ => continue,
+        };
+        if socket.send(send(reply)).await.is_err() {
+            return;
+        }
+    }
+
+    let ready = json!({
+        "application": { "id": BOT_ID.to_string(), "flags": 0 },
+        "guilds": [
+            { "id": GUILD_ID.to_string(), "unavailable": true },
+            { "id": OTHER_GUILD_ID.to_string(), "unavailable": true },
+        ],
+        "session_id": "fake",
+        "user": {
+            "id": BOT_ID.to_string(),
+            "username": "Crabe",
+            "discriminator": "0001",
+            "avatar": null,
+            "bot": true,
+            "mfa_enabled": false,
+        },
+        "v": 10,
+    });
+    let mut sequence = 1;
+    if socket
+        .send(send(
+            json!({ "op": 0, "s": sequence, "t": "READY", "d": ready }),
+        ))
+        .await
+        .is_err()
+    {
+        return;
+    }
+
+    let mut events = shared.events.lock().await;
+    loop {
+        tokio::select! {
+            received = socket.next() => match received {
+                Some(Ok(WsMessage::Text(text))) if opcode(&text) == Some(1) => {
+                    if socket.send(send(json!({ "op": 11 }))).await.is_err() {
+                        return;
+                    }
+                }
+                Some(Ok(WsMessage::Close(_))) | Some(Err(_)) | None => return,
+                Some(Ok(_)) => {}
+            },
+            Some((event, data)) = events.recv() => {
+                sequence += 1;
+                let dispatch = json!({ "op": 0, "s": sequence, "t": event, "d": data });
+                if socket.send(send(dispatch)).await.is_err() {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+fn opcode(text: &str) -> Option<u64> {
+    serde_json::from_str::<Value>(text).ok()?["op"].as_u64()
+}
+
+/// Answers the few REST API routes the bot uses, recording the messages it sends.
+async fn serve_rest(
+    request: Request<Body>,
+    shared: Arc<Shared>,
+) -> Result<Response<Body>, Infallible> {
+    let method = request.method().clone();
+    let path = request.uri().path().to_string();
+    let body = hyper::body::to_bytes(request.into_body())
+        .await
+        .unwrap_or_default();
+    let segments = path
+        .trim_start_matches("/api/v10/")
+        .split('/')
+        .collect::<Vec<_>>();
+
+    let response = match (&method, segments.as_slice()) {
+        (&Method::GET, ["gateway"]) => Some(json!({ "url": shared.gateway_url })),
+        (&Method::PUT, ["applications", _, "commands"]) => Some(json!([])),
+        (&Method::POST, ["channels", channel_id, "messages"]) => {
+            let channel_id = channel_id.parse().unwrap();
+            let body = serde_json::from_slice::<Value>(&body).unwrap_or_default();
+            shared.sent.lock().unwrap().push(SentMessage {
+                channel_id,
+                body: body.clone(),
+            });
+
+            let id = shared.next_id.fetch_add(1, Ordering::SeqCst);
+            let content = body["content"].as_str().unwrap_or_default();
+            Some(message(
+                id,
+                channel_id,
+                user(BOT_ID, "Crabe", true),
+                content,
+                Utc::now(),
+            ))
+        }
+        (&Method::POST, ["interactions", interaction_id, _, "callback"]) => {
+            let interaction_id = interaction_id.parse().unwrap();
+            let body = serde_json::from_slice::<Value>(&body).unwrap_or_default();
+            if body["type"] == DEFERRED_CHANNEL_MESSAGE {
+                shared
+                    .deferred
+                    .lock()
+                    .unwrap()
+                    .insert(interaction_id, body["data"].clone());
+            } else {
+                shared
+                    .responses
+                    .lock()
+                    .unwrap()
+                    .insert(interaction_id, body);
+            }
+
+            return Ok(Response::builder()
+                .status(StatusCode::NO_CONTENT)
+                .body(Body::empty())
+                .unwrap());
+        }
+        (&Method::PATCH, ["webhooks", _, token, "messages", "@original"]) => {
+            let interaction_id = token.trim_start_matches("token-").parse().unwrap();
+            let mut body = serde_json::from_slice::<Value>(&body).unwrap_or_default();
+            let content = body["content"].as_str().unwrap_or_default().to_string();
+            // The edited response stays ephemeral if it was deferred as such.
+            if let Some(deferred) = shared.deferred.lock().unwrap().get(&interaction_id) {
+                body["flags"] = deferred["flags"].clone();
+            }
+            shared.responses.lock().unwrap().insert(
+                interaction_id,
+                json!({ "type": CHANNEL_MESSAGE, "data": body }),
+            );
+
+            let id = shared.next_id.fetch_add(1, Ordering::SeqCst);
+            Some(message(
+                id,
+                0,
+                user(BOT_ID, "Crabe", true),
+                &content,
+                Utc::now(),
+            ))
+        }
+        (&Method::GET, ["users", user_id]) => user_id
+            .parse::<u64>()
+            .ok()
+            .and_then(|user_id| shared.users.lock().unwrap().get(&user_id).cloned()),
+        _ => None,
+    };
+
+    Ok(match response {
+        Some(response) => Response::new(Body::from(response.to_string())),
+        None => Response::builder()
+            .status(StatusCode::NOT_FOUND)
+            .body(Body::from(
+                json!({ "message": "Unknown", "code": 0 }).to_string(),
+            ))
+            .unwrap(),
+    })
+}
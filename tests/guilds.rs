@@ -0,0 +1,75 @@
+mod fake_discord;
+
+use std::{sync::Arc, time::Duration as StdDuration};
+
+use chrono::{Duration, TimeZone, Utc};
+use crabe_core::clock::MockClock;
+use crabe_de_la_crabe::storage::{SqliteStorage, Storage};
+use fake_discord::{eventually, mention_count_in, Author, FakeDiscord, GUILD_ID, OTHER_GUILD_ID};
+use serde_json::json;
+use serenity::model::{prelude::GuildId, Permissions};
+
+const CHANNEL_ID: u64 = 20;
+const OTHER_CHANNEL_ID: u64 = 40;
+
+const FERRIS: Author = Author {
+    id: 100,
+    name: "ferris",
+    bot: false,
+};
+
+#[tokio::test(flavor = "multi_thread")]
+async fn a_mention_in_one_guild_leaves_the_other_untouched() {
+    let discord = FakeDiscord::start().await;
+    let start = Utc.with_ymd_and_hms(2023, 1, 2, 9, 0, 0).unwrap();
+    let clock = Arc::new(MockClock::new(start + Duration::days(3)));
+    let storage = Arc::new(SqliteStorage::open(":memory:").unwrap());
+    discord.start_bot(storage.clone(), clock).await;
+    let other_guild = json!({ "guild_id": OTHER_GUILD_ID.to_string() });
+
+    discord.send_message(CHANNEL_ID, &FERRIS, "Rust!", start);
+    discord.send_message_with(OTHER_CHANNEL_ID, &FERRIS, "Rust!", start, other_guild);
+    eventually("the first mentions", || {
+        mention_count_in(storage.as_ref(), GUILD_ID, &FERRIS) == 1
+            && mention_count_in(storage.as_ref(), OTHER_GUILD_ID, &FERRIS) == 1
+    })
+    .await;
+
+    discord.send_message(CHANNEL_ID, &FERRIS, "Rust!", start + Duration::days(2));
+    eventually("the second mention", || {
+        mention_count_in(storage.as_ref(), GUILD_ID, &FERRIS) == 2
+    })
+    .await;
+
+    assert_eq!(
+        mention_count_in(storage.as_ref(), OTHER_GUILD_ID, &FERRIS),
+        1
+    );
+    let records = storage.load_records().unwrap();
+    assert_eq!(
+        records[&GuildId(GUILD_ID)].duration,
+        Some(StdDuration::from_secs(2 * 24 * 60 * 60))
+    );
+    assert_eq!(
+        records[&GuildId(OTHER_GUILD_ID)].duration,
+        Some(StdDuration::ZERO)
+    );
+    assert_eq!(records[&GuildId(OTHER_GUILD_ID)].last_mention, Some(start));
+    let channel_records = storage.load_channel_records().unwrap();
+    assert!(!channel_records[&GuildId(OTHER_GUILD_ID)].contains_key(&CHANNEL_ID.into()));
+
+    let interaction = discord.use_command_in(
+        OTHER_GUILD_ID,
+        OTHER_CHANNEL_ID,
+        &FERRIS,
+        Permissions::SEND_MESSAGES,
+        &[],
+        json!({ "name": "since" }),
+    );
+    let response = discord.wait_for_response(interaction).await;
+    assert_eq!(
+        response["embeds"][0]["description"],
+        "It has been 3 days since somebody last mentioned Rust on this server.\n\n\
+         The record on this server is 0 seconds. You are setting a new record right now!"
+    );
+}
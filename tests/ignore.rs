@@ -0,0 +1,200 @@
+mod fake_discord;
+
+use std::sync::Arc;
+
+use chrono::{Duration, TimeZone, Utc};
+use crabe_core::clock::MockClock;
+use crabe_de_la_crabe::storage::{SqliteStorage, Storage};
+use fake_discord::{eventually, mention_count, Author, FakeDiscord, GUILD_ID};
+use serde_json::{json, Value};
+use serenity::model::prelude::GuildId;
+
+const CHANNEL_ID: u64 = 20;
+
+const FERRIS: Author = Author {
+    id: 100,
+    name: "ferris",
+    bot: false,
+};
+const CORRO: Author = Author {
+    id: 102,
+    name: "corro",
+    bot: false,
+};
+const RELEASES: Author = Author {
+    id: 103,
+    name: "releases",
+    bot: false,
+};
+
+const MODERATOR: u64 = 500;
+const CRABS: u64 = 501;
+
+/// Starts the bot on a guild with the given settings overrides.
+async fn start_with(overrides: Value) -> (FakeDiscord, Arc<SqliteStorage>) {
+    let discord = FakeDiscord::start().await;
+    let clock = Arc::new(MockClock::new(
+        Utc.with_ymd_and_hms(2023, 1, 2, 9, 0, 0).unwrap(),
+    ));
+    let storage = Arc::new(SqliteStorage::open(":memory:").unwrap());
+    storage
+        .save_setting_overrides(GuildId(GUILD_ID), overrides.as_object().unwrap())
+        .unwrap();
+    discord.start_bot(storage.clone(), clock).await;
+    (discord, storage)
+}
+
+/// The member sending a message, who has the given roles.
+fn member(roles: &[u64]) -> Value {
+    json!({
+        "member": {
+            "roles": roles.iter().map(u64::to_string).collect::<Vec<_>>(),
+            "joined_at": "2021-01-01T00:00:00+00:00",
+            "deaf": false,
+            "mute": false,
+        },
+    })
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn messages_sent_through_webhooks_are_ignored() {
+    let (discord, storage) = start_with(json!({})).await;
+    let start = Utc.with_ymd_and_hms(2023, 1, 2, 9, 0, 0).unwrap();
+
+    discord.send_message_with(
+        CHANNEL_ID,
+        &RELEASES,
+        "Rust 1.66 is out!",
+        start,
+        json!({ "webhook_id": RELEASES.id.to_string() }),
+    );
+    discord.send_message(CHANNEL_ID, &CORRO, "Rust!", start + Duration::hours(1));
+    eventually("the mention", || {
+        mention_count(storage.as_ref(), &CORRO) == 1
+    })
+    .await;
+
+    assert_eq!(mention_count(storage.as_ref(), &RELEASES), 0);
+    assert_eq!(storage.load_mentions(GuildId(GUILD_ID)).unwrap().len(), 1);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn configured_webhooks_are_ignored_when_webhooks_are_counted() {
+    let (discord, storage) = start_with(json!({
+        "ignore": { "webhooks": false, "users": [RELEASES.id.to_string()] },
+    }))
+    .await;
+    let start = Utc.with_ymd_and_hms(2023, 1, 2, 9, 0, 0).unwrap();
+
+    discord.send_message_with(
+        CHANNEL_ID,
+        &RELEASES,
+        "Rust 1.66 is out!",
+        start,
+        json!({ "webhook_id": RELEASES.id.to_string() }),
+    );
+    discord.send_message_with(
+        CHANNEL_ID,
+        &CORRO,
+        "Rust!",
+        start + Duration::hours(1),
+        json!({ "webhook_id": CORRO.id.to_string() }),
+    );
+    eventually("the webhook mention", || {
+        mention_count(storage.as_ref(), &CORRO) == 1
+    })
+    .await;
+
+    assert_eq!(mention_count(storage.as_ref(), &RELEASES), 0);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn system_messages_are_ignored() {
+    let (discord, storage) = start_with(json!({})).await;
+    let start = Utc.with_ymd_and_hms(2023, 1, 2, 9, 0, 0).unwrap();
+
+    // A pinned message notification.
+    discord.send_message_with(CHANNEL_ID, &FERRIS, "Rust", start, json!({ "type": 6 }));
+    // Replies are written by members, so they still count.
+    discord.send_message_with(
+        CHANNEL_ID,
+        &CORRO,
+        "Rust!",
+        start + Duration::hours(1),
+        json!({ "type": 19 }),
+    );
+    eventually("the reply", || mention_count(storage.as_ref(), &CORRO) == 1).await;
+
+    assert_eq!(mention_count(storage.as_ref(), &FERRIS), 0);
+    assert_eq!(storage.load_mentions(GuildId(GUILD_ID)).unwrap().len(), 1);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn configured_users_are_ignored() {
+    let (discord, storage) = start_with(json!({
+        "ignore": { "users": [FERRIS.id.to_string()] },
+    }))
+    .await;
+    let start = Utc.with_ymd_and_hms(2023, 1, 2, 9, 0, 0).unwrap();
+
+    let message = discord.send_message(CHANNEL_ID, &FERRIS, "Rust!", start);
+    discord.react(
+        CHANNEL_ID,
+        message,
+        &FERRIS,
+        &[],
+        json!({ "id": null, "name": "🦀" }),
+    );
+    discord.send_message(CHANNEL_ID, &CORRO, "Rust!", start + Duration::hours(1));
+    eventually("the mention", || {
+        mention_count(storage.as_ref(), &CORRO) == 1
+    })
+    .await;
+
+    assert_eq!(mention_count(storage.as_ref(), &FERRIS), 0);
+    assert_eq!(storage.load_mentions(GuildId(GUILD_ID)).unwrap().len(), 1);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn members_with_configured_roles_are_ignored() {
+    let (discord, storage) = start_with(json!({
+        "ignore": { "roles": [MODERATOR.to_string()] },
+    }))
+    .await;
+    let start = Utc.with_ymd_and_hms(2023, 1, 2, 9, 0, 0).unwrap();
+
+    let message =
+        discord.send_message_with(CHANNEL_ID, &FERRIS, "Rust!", start, member(&[MODERATOR]));
+    discord.react(
+        CHANNEL_ID,
+        message,
+        &FERRIS,
+        &[CRABS, MODERATOR],
+        json!({ "id": null, "name": "🦀" }),
+    );
+    discord.send_message_with(
+        CHANNEL_ID,
+        &CORRO,
+        "Rust!",
+        start + Duration::hours(1),
+        member(&[CRABS]),
+    );
+    eventually("the message of a member without the role", || {
+        mention_count(storage.as_ref(), &CORRO) == 1
+    })
+    .await;
+    discord.react(
+        CHANNEL_ID,
+        message,
+        &CORRO,
+        &[CRABS],
+        json!({ "id": null, "name": "🦀" }),
+    );
+    eventually("the reaction of a member without the role", || {
+        mention_count(storage.as_ref(), &CORRO) == 2
+    })
+    .await;
+
+    assert_eq!(mention_count(storage.as_ref(), &FERRIS), 0);
+    assert_eq!(storage.load_mentions(GuildId(GUILD_ID)).unwrap().len(), 2);
+}
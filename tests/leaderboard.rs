@@ -0,0 +1,271 @@
+mod fake_discord;
+
+use std::sync::Arc;
+
+use chrono::{Duration, TimeZone, Utc};
+use crabe_core::{clock::MockClock, leaderboard::Window};
+use crabe_de_la_crabe::{commands::leaderboard::PageRequest, storage::SqliteStorage};
+use fake_discord::{eventually, mention_count, Author, FakeDiscord};
+use serde_json::{json, Value};
+use serenity::model::Permissions;
+
+const CHANNEL_ID: u64 = 20;
+
+/// More users than fit on a single page of the leaderboard.
+const AUTHORS: [Author; 12] = [
+    Author {
+        id: 100,
+        name: "ferris",
+        bot: false,
+    },
+    Author {
+        id: 101,
+        name: "corro",
+        bot: false,
+    },
+    Author {
+        id: 102,
+        name: "bors",
+        bot: false,
+    },
+    Author {
+        id: 103,
+        name: "clippy",
+        bot: false,
+    },
+    Author {
+        id: 104,
+        name: "miri",
+        bot: false,
+    },
+    Author {
+        id: 105,
+        name: "cargo",
+        bot: false,
+    },
+    Author {
+        id: 106,
+        name: "rustc",
+        bot: false,
+    },
+    Author {
+        id: 107,
+        name: "rustup",
+        bot: false,
+    },
+    Author {
+        id: 108,
+        name: "rustdoc",
+        bot: false,
+    },
+    Author {
+        id: 109,
+        name: "rustfmt",
+        bot: false,
+    },
+    Author {
+        id: 110,
+        name: "crater",
+        bot: false,
+    },
+    Author {
+        id: 111,
+        name: "triagebot",
+        bot: false,
+    },
+];
+
+/// The data of `/leaderboard` with the given options.
+fn leaderboard(options: Value) -> Value {
+    json!({ "name": "leaderboard", "options": options })
+}
+
+/// The custom IDs of the buttons of a response, and whether each of them is disabled.
+fn buttons(response: &Value) -> Vec<(String, bool)> {
+    response["components"][0]["components"]
+        .as_array()
+        .map(|buttons| {
+            buttons
+                .iter()
+                .map(|button| {
+                    (
+                        button["custom_id"].as_str().unwrap().to_string(),
+                        button["disabled"].as_bool().unwrap_or_default(),
+                    )
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn footer(response: &Value) -> &str {
+    response["embeds"][0]["footer"]["text"].as_str().unwrap()
+}
+
+#[test]
+fn page_requests_round_trip_through_custom_ids() {
+    let custom = Window::custom("2023-01-02", "2023-01-08").unwrap();
+    let requests = [
+        (Window::AllTime, "leaderboard:all:1"),
+        (Window::Month, "leaderboard:month:1"),
+        (Window::Week, "leaderboard:week:1"),
+        (custom, "leaderboard:custom:1:1672617600:1673222400"),
+    ];
+
+    for (window, custom_id) in requests {
+        let request = PageRequest { window, page: 1 };
+        assert_eq!(request.to_string(), custom_id);
+        assert_eq!(custom_id.parse::<PageRequest>(), Ok(request));
+
+        let next = request.with_page(3);
+        assert_eq!(next.to_string().parse::<PageRequest>(), Ok(next));
+    }
+
+    for custom_id in [
+        "",
+        "other:all:1",
+        "leaderboard:year:1",
+        "leaderboard:all:first",
+        "leaderboard:custom:1:1672617600",
+    ] {
+        assert_eq!(custom_id.parse::<PageRequest>(), Err(()));
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn the_leaderboard_is_paginated_with_buttons() {
+    let discord = FakeDiscord::start().await;
+    let start = Utc.with_ymd_and_hms(2023, 1, 2, 9, 0, 0).unwrap();
+    let clock = Arc::new(MockClock::new(start + Duration::hours(1)));
+    let storage = Arc::new(SqliteStorage::open(":memory:").unwrap());
+    discord.start_bot(storage.clone(), clock).await;
+
+    // The last author mentions Rust twice, everybody else once.
+    for author in &AUTHORS {
+        discord.send_message(CHANNEL_ID, author, "Rust!", start);
+    }
+    discord.send_message(CHANNEL_ID, &AUTHORS[11], "Rust again!", start);
+    eventually("the mentions", || {
+        AUTHORS
+            .iter()
+            .map(|author| mention_count(storage.as_ref(), author))
+            .sum::<usize>()
+            == 13
+    })
+    .await;
+
+    let interaction = discord.use_command(
+        CHANNEL_ID,
+        &AUTHORS[0],
+        Permissions::SEND_MESSAGES,
+        &[],
+        leaderboard(json!([])),
+    );
+    let response = discord.wait_for_response(interaction).await;
+    let description = response["embeds"][0]["description"].as_str().unwrap();
+    assert!(description.starts_with(
+        "1. **triagebot**: 2 mentions\n2. **ferris**: 1 mention\n3. **corro**: 1 mention\n"
+    ));
+    assert!(description.contains("10. **rustdoc**: 1 mention\n"));
+    assert!(!description.contains("rustfmt"));
+    assert!(footer(&response).starts_with("Page 1 of 2"));
+    assert_eq!(
+        buttons(&response),
+        vec![
+            ("leaderboard:all:1".to_string(), true),
+            ("leaderboard:all:2".to_string(), false),
+        ]
+    );
+
+    let interaction = discord.press_button(CHANNEL_ID, &AUTHORS[0], "leaderboard:all:2");
+    let response = discord.wait_for_response(interaction).await;
+    assert_eq!(
+        response["embeds"][0]["description"],
+        "11. **rustfmt**: 1 mention\n12. **crater**: 1 mention\n"
+    );
+    assert!(footer(&response).starts_with("Page 2 of 2"));
+    assert_eq!(
+        buttons(&response),
+        vec![
+            ("leaderboard:all:1".to_string(), false),
+            ("leaderboard:all:2".to_string(), true),
+        ]
+    );
+
+    // Pages past the end show the last one.
+    let interaction = discord.use_command(
+        CHANNEL_ID,
+        &AUTHORS[0],
+        Permissions::SEND_MESSAGES,
+        &[],
+        leaderboard(json!([{ "name": "page", "type": 4, "value": 7 }])),
+    );
+    let response = discord.wait_for_response(interaction).await;
+    assert!(footer(&response).starts_with("Page 2 of 2"));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn custom_date_ranges_are_kept_across_pages() {
+    let discord = FakeDiscord::start().await;
+    let start = Utc.with_ymd_and_hms(2023, 1, 2, 9, 0, 0).unwrap();
+    let clock = Arc::new(MockClock::new(start + Duration::days(3)));
+    let storage = Arc::new(SqliteStorage::open(":memory:").unwrap());
+    discord.start_bot(storage.clone(), clock).await;
+
+    // Only the first eleven authors mention Rust within the range.
+    for author in &AUTHORS[..11] {
+        discord.send_message(CHANNEL_ID, author, "Rust!", start);
+    }
+    discord.send_message(CHANNEL_ID, &AUTHORS[11], "Rust!", start + Duration::days(2));
+    eventually("the mentions", || {
+        mention_count(storage.as_ref(), &AUTHORS[11]) == 1
+            && mention_count(storage.as_ref(), &AUTHORS[10]) == 1
+    })
+    .await;
+
+    let custom = |from: &str, to: &str| {
+        leaderboard(json!([
+            { "name": "window", "type": 3, "value": "custom" },
+            { "name": "from", "type": 3, "value": from },
+            { "name": "to", "type": 3, "value": to },
+        ]))
+    };
+    let interaction = discord.use_command(
+        CHANNEL_ID,
+        &AUTHORS[0],
+        Permissions::SEND_MESSAGES,
+        &[],
+        custom("2023-01-01", "2023-01-03"),
+    );
+    let response = discord.wait_for_response(interaction).await;
+    assert_eq!(
+        response["embeds"][0]["title"],
+        "🦀 Rust Leaderboard: 2023-01-01 to 2023-01-03 🦀"
+    );
+    let (next, _) = buttons(&response).pop().unwrap();
+    assert_eq!(next, "leaderboard:custom:2:1672531200:1672790400");
+
+    let interaction = discord.press_button(CHANNEL_ID, &AUTHORS[0], &next);
+    let response = discord.wait_for_response(interaction).await;
+    assert_eq!(
+        response["embeds"][0]["title"],
+        "🦀 Rust Leaderboard: 2023-01-01 to 2023-01-03 🦀"
+    );
+    assert_eq!(
+        response["embeds"][0]["description"],
+        "11. **crater**: 1 mention\n"
+    );
+
+    let interaction = discord.use_command(
+        CHANNEL_ID,
+        &AUTHORS[0],
+        Permissions::SEND_MESSAGES,
+        &[],
+        custom("2023-01-03", "2023-01-01"),
+    );
+    let response = discord.wait_for_response(interaction).await;
+    assert_eq!(
+        response["content"],
+        "The start date has to be before the end date."
+    );
+}
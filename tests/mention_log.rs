@@ -0,0 +1,83 @@
+use std::{sync::Arc, time::Duration as StdDuration};
+
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use crabe_core::{clock::MockClock, sources::MentionSource};
+use crabe_de_la_crabe::{
+    state::{GuildStates, Mention},
+    storage::{SqliteStorage, Storage},
+};
+use serenity::model::prelude::{ChannelId, GuildId, MessageId, UserId};
+
+const GUILD_ID: GuildId = GuildId(10);
+const FERRIS: UserId = UserId(100);
+const CORRO: UserId = UserId(102);
+
+const DAY: u64 = 24 * 60 * 60;
+
+fn mention(message_id: u64, channel_id: u64, user_id: UserId, sent_at: DateTime<Utc>) -> Mention {
+    Mention {
+        guild_id: GUILD_ID,
+        channel_id: ChannelId(channel_id),
+        user_id,
+        message_id: MessageId(message_id),
+        source: MentionSource::Text,
+        weight: 1,
+        sent_at,
+        rule: Some("rust".to_string()),
+    }
+}
+
+#[tokio::test]
+async fn compacting_the_log_keeps_what_it_adds_up_to() {
+    let start = Utc.with_ymd_and_hms(2023, 1, 2, 9, 0, 0).unwrap();
+    let clock = Arc::new(MockClock::new(start));
+    let storage = Arc::new(SqliteStorage::open(":memory:").unwrap());
+    let states = GuildStates::load(storage.clone(), clock.clone()).unwrap();
+
+    states
+        .track(vec![mention(1, 20, FERRIS, start)], false)
+        .await
+        .unwrap();
+    states
+        .track(
+            vec![mention(2, 21, CORRO, start + Duration::days(2))],
+            false,
+        )
+        .await
+        .unwrap();
+    clock.set(start + Duration::days(3));
+    assert_eq!(states.set_mention_count(GUILD_ID, CORRO, 5).await, 1);
+    states
+        .track(
+            vec![mention(3, 20, FERRIS, start + Duration::days(4))],
+            false,
+        )
+        .await
+        .unwrap();
+
+    let counts = states.mention_counts(GUILD_ID).await;
+    let record = states.record(GUILD_ID, None).await;
+    assert_eq!(counts[&FERRIS], 2);
+    assert_eq!(counts[&CORRO], 5);
+    assert_eq!(record.duration, Some(StdDuration::from_secs(2 * DAY)));
+
+    // The mentions and the correction before the cutoff are folded into the baseline, so
+    // rebuilding from what is left of the log arrives at the same counts and records.
+    let cutoff = start + Duration::days(3) + Duration::hours(1);
+    assert_eq!(states.compact(GUILD_ID, cutoff).await, 2);
+    assert_eq!(storage.load_mentions(GUILD_ID).unwrap().len(), 1);
+    assert!(storage.load_corrections(GUILD_ID).unwrap().is_empty());
+
+    assert_eq!(states.rebuild(GUILD_ID, None).await, Some(0));
+    assert_eq!(states.mention_counts(GUILD_ID).await, counts);
+    assert_eq!(states.record(GUILD_ID, None).await, record);
+    assert_eq!(storage.load_mention_counts().unwrap()[&GUILD_ID][&CORRO], 5);
+
+    // Compacted mentions can no longer be taken back, but the ones left in the log can.
+    assert!(states.disqualify(GUILD_ID, MessageId(2)).await.is_none());
+    let disqualified = states.disqualify(GUILD_ID, MessageId(3)).await.unwrap();
+    assert_eq!(disqualified.weights[&FERRIS], 1);
+    assert_eq!(states.mention_counts(GUILD_ID).await[&FERRIS], 1);
+    let channel_record = states.record(GUILD_ID, Some(ChannelId(20))).await;
+    assert_eq!(channel_record.last_mention, Some(start));
+}
@@ -0,0 +1,77 @@
+mod fake_discord;
+
+use std::sync::Arc;
+
+use chrono::{Duration, TimeZone, Utc};
+use crabe_core::clock::{Clock, MockClock};
+use crabe_de_la_crabe::storage::{SqliteStorage, Storage};
+use fake_discord::{eventually, mention_count, Author, FakeDiscord, GUILD_ID};
+use serde_json::json;
+use serenity::model::prelude::GuildId;
+
+const CHANNEL_ID: u64 = 20;
+const REPORT_CHANNEL_ID: u64 = 30;
+const TITLE: &str = "🦀 Rust Report 🦀";
+
+const FERRIS: Author = Author {
+    id: 100,
+    name: "ferris",
+    bot: false,
+};
+const CORRO: Author = Author {
+    id: 102,
+    name: "corro",
+    bot: false,
+};
+
+#[tokio::test(flavor = "multi_thread")]
+async fn the_weekly_report_ranks_the_mentions_once_it_is_due() {
+    let discord = FakeDiscord::start().await;
+    // The default schedule posts the reports every Monday at 09:00 UTC.
+    let last_report = Utc.with_ymd_and_hms(2023, 1, 2, 9, 0, 0).unwrap();
+    let clock = Arc::new(MockClock::new(last_report + Duration::hours(1)));
+    let storage = Arc::new(SqliteStorage::open(":memory:").unwrap());
+    let overrides = json!({
+        "report_channel": REPORT_CHANNEL_ID,
+        "announce_milestones": false,
+    });
+    storage
+        .save_setting_overrides(GuildId(GUILD_ID), overrides.as_object().unwrap())
+        .unwrap();
+    storage
+        .save_last_report(GuildId(GUILD_ID), last_report)
+        .unwrap();
+    discord.start_bot(storage.clone(), clock.clone()).await;
+
+    let now = clock.now();
+    discord.send_message(CHANNEL_ID, &FERRIS, "Rust!", now);
+    eventually("the first mention", || {
+        mention_count(storage.as_ref(), &FERRIS) == 1
+    })
+    .await;
+    discord.send_message(CHANNEL_ID, &FERRIS, "More Rust!", now + Duration::hours(1));
+    eventually("the second mention", || {
+        mention_count(storage.as_ref(), &FERRIS) == 2
+    })
+    .await;
+    discord.send_message(CHANNEL_ID, &CORRO, "Rust, again?", now + Duration::days(2));
+    eventually("the third mention", || {
+        mention_count(storage.as_ref(), &CORRO) == 1
+    })
+    .await;
+    assert!(discord
+        .sent_messages()
+        .iter()
+        .all(|message| message.embed_title() != Some(TITLE)));
+
+    clock.advance(Duration::days(7));
+
+    let reports = discord.wait_for_embeds(TITLE, 1).await;
+    assert_eq!(reports[0].channel_id, REPORT_CHANNEL_ID);
+    let report = reports[0].embed_description().unwrap();
+    assert!(
+        report.contains("**ferris**: 2 mentions\n**corro**: 1 mention\n"),
+        "{}",
+        report
+    );
+}
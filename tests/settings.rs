@@ -0,0 +1,275 @@
+use std::sync::Arc;
+
+use crabe_de_la_crabe::{
+    config::{Config, ConfigError},
+    settings::{SettingChange, Settings, SettingsError},
+    storage::{SqliteStorage, Storage},
+};
+use serde_json::json;
+use serenity::model::prelude::{ChannelId, GuildId};
+
+const GUILD_ID: GuildId = GuildId(10);
+const OTHER_GUILD_ID: GuildId = GuildId(11);
+
+const CONFIG: &str = r#"
+[defaults]
+report_schedule = "0 18 * * Fri"
+timezone = "Europe/Berlin"
+
+[defaults.embed]
+footer = "Counted by crabs"
+
+[guilds.10]
+report_channel_name = "general"
+timezone = "America/New_York"
+"#;
+
+fn settings(config: &str) -> Settings {
+    let storage = Arc::new(SqliteStorage::open(":memory:").unwrap());
+    Settings::new(storage, Config::parse(config).unwrap())
+}
+
+#[test]
+fn the_config_file_overrides_the_defaults_for_all_or_single_guilds() {
+    let settings = settings(CONFIG);
+
+    let guild = settings.load(GUILD_ID).unwrap();
+    assert_eq!(guild.report_schedule, "0 18 * * Fri");
+    assert_eq!(guild.timezone, "America/New_York");
+    assert_eq!(guild.report_channel_name, "general");
+    assert_eq!(guild.embed.footer, "Counted by crabs");
+    assert_eq!(guild.embed.color, 0xdea584);
+
+    let other = settings.load(OTHER_GUILD_ID).unwrap();
+    assert_eq!(other.timezone, "Europe/Berlin");
+    assert_eq!(other.report_channel_name, "random");
+}
+
+#[test]
+fn settings_changed_by_a_guild_override_the_config_file() {
+    let settings = settings(CONFIG);
+
+    settings.set(GUILD_ID, "timezone", "Asia/Tokyo").unwrap();
+    settings.set(GUILD_ID, "embed.color", "16711680").unwrap();
+    settings.set(GUILD_ID, "report_channel", "42").unwrap();
+
+    let guild = settings.load(GUILD_ID).unwrap();
+    assert_eq!(guild.timezone, "Asia/Tokyo");
+    assert_eq!(guild.embed.color, 0xff0000);
+    assert_eq!(guild.embed.footer, "Counted by crabs");
+    assert_eq!(guild.report_channel, Some(ChannelId(42)));
+}
+
+#[test]
+fn reloading_the_config_file_affects_everything_a_guild_did_not_change() {
+    let settings = settings(CONFIG);
+    settings.set(GUILD_ID, "timezone", "Asia/Tokyo").unwrap();
+
+    settings.reload(
+        Config::parse(
+            r#"
+            [defaults]
+            timezone = "Europe/Paris"
+            report_schedule = "0 8 * * Mon"
+            "#,
+        )
+        .unwrap(),
+    );
+
+    let guild = settings.load(GUILD_ID).unwrap();
+    assert_eq!(guild.timezone, "Asia/Tokyo");
+    assert_eq!(guild.report_schedule, "0 8 * * Mon");
+    assert_eq!(guild.report_channel_name, "random");
+}
+
+#[test]
+fn changes_report_the_values_before_and_after() {
+    let settings = settings(CONFIG);
+
+    assert_eq!(
+        settings.set(GUILD_ID, "timezone", "Asia/Tokyo").unwrap(),
+        vec![SettingChange {
+            key: "timezone".to_string(),
+            old_value: json!("America/New_York"),
+            new_value: json!("Asia/Tokyo"),
+        }]
+    );
+    assert_eq!(
+        settings.set(GUILD_ID, "timezone", "Asia/Tokyo").unwrap(),
+        Vec::new()
+    );
+}
+
+#[test]
+fn invalid_changes_are_rejected() {
+    let settings = settings("");
+
+    assert!(matches!(
+        settings.set(GUILD_ID, "report_shedule", "0 9 * * *"),
+        Err(SettingsError::Invalid(reason)) if reason == "There is no setting called `report_shedule`."
+    ));
+    assert!(matches!(
+        settings.set(GUILD_ID, "embed.colour", "0"),
+        Err(SettingsError::Invalid(reason)) if reason == "There is no setting called `embed.colour`."
+    ));
+    assert!(matches!(
+        settings.set(GUILD_ID, "timezone", "Mars/Olympus_Mons"),
+        Err(SettingsError::Invalid(_))
+    ));
+    assert_eq!(settings.load(GUILD_ID).unwrap().timezone, "UTC");
+}
+
+#[test]
+fn resolved_settings_are_cached_until_they_change() {
+    let storage = Arc::new(SqliteStorage::open(":memory:").unwrap());
+    let settings = Settings::new(storage.clone(), Config::parse(CONFIG).unwrap());
+    assert_eq!(
+        settings.load(GUILD_ID).unwrap().timezone,
+        "America/New_York"
+    );
+
+    // Writing the storage directly bypasses the cache.
+    storage
+        .save_setting_overrides(
+            GUILD_ID,
+            json!({ "timezone": "Asia/Tokyo" }).as_object().unwrap(),
+        )
+        .unwrap();
+    assert_eq!(
+        settings.load(GUILD_ID).unwrap().timezone,
+        "America/New_York"
+    );
+
+    settings
+        .set(GUILD_ID, "report_channel_name", "crabs")
+        .unwrap();
+    let guild = settings.load(GUILD_ID).unwrap();
+    assert_eq!(guild.report_channel_name, "crabs");
+    assert_eq!(guild.timezone, "Asia/Tokyo");
+
+    assert_eq!(
+        settings.load(OTHER_GUILD_ID).unwrap().report_schedule,
+        "0 18 * * Fri"
+    );
+    settings.reload(Config::default());
+    assert_eq!(
+        settings.load(OTHER_GUILD_ID).unwrap().report_schedule,
+        "0 9 * * Mon"
+    );
+}
+
+#[test]
+fn concurrent_changes_and_reloads_keep_every_update() {
+    let storage = Arc::new(SqliteStorage::open(":memory:").unwrap());
+    let settings = Settings::new(storage.clone(), Config::default());
+    let config = Config::parse(CONFIG).unwrap();
+
+    std::thread::scope(|scope| {
+        scope.spawn(|| {
+            for seconds in 0..50 {
+                settings
+                    .set_value(GUILD_ID, "deletion_grace_seconds", json!(seconds))
+                    .unwrap();
+            }
+        });
+        scope.spawn(|| {
+            for i in 0..50 {
+                settings
+                    .set(GUILD_ID, "report_channel_name", &format!("crabs-{}", i))
+                    .unwrap();
+            }
+        });
+        scope.spawn(|| {
+            for _ in 0..25 {
+                settings.reload(Config::default());
+                settings.reload(config.clone());
+            }
+        });
+    });
+
+    let guild = settings.load(GUILD_ID).unwrap();
+    assert_eq!(guild.deletion_grace_seconds, 49);
+    assert_eq!(guild.report_channel_name, "crabs-49");
+    // Nothing resolved from a replaced config file is left in the cache.
+    assert_eq!(guild.timezone, "America/New_York");
+    let fresh = Settings::new(storage, config).load(GUILD_ID).unwrap();
+    assert_eq!(
+        serde_json::to_value(guild).unwrap(),
+        serde_json::to_value(fresh).unwrap()
+    );
+}
+
+#[test]
+fn invalid_config_files_are_reported_by_section() {
+    let error = |config| match Config::parse(config) {
+        Err(ConfigError::Invalid { section, reason }) => (section, reason),
+        other => panic!("Expected the config to be invalid, got {:?}.", other),
+    };
+
+    assert_eq!(
+        error("[defaults]\nreport_interval = 7"),
+        (
+            "defaults".to_string(),
+            "There is no setting called `report_interval`.".to_string()
+        )
+    );
+    assert_eq!(error("[guilds.10]\ntimezone = \"Nowhere\"").0, "guilds.10");
+    assert_eq!(
+        error("[guilds.general]\ntimezone = \"UTC\""),
+        (
+            "guilds.general".to_string(),
+            "`general` is not the ID of a guild.".to_string()
+        )
+    );
+    assert!(matches!(
+        Config::parse("[default]"),
+        Err(ConfigError::Toml(_))
+    ));
+}
+
+#[test]
+fn settings_saved_before_only_keep_what_differs_from_the_defaults() {
+    let path = std::env::temp_dir().join(format!("crabe-settings-{}.sqlite3", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+
+    // Fake a database from before the settings were layered, which stored them as a whole.
+    SqliteStorage::open(&path).unwrap();
+    let legacy = r#"{"report_channel":null,"report_schedule":"0 9 * * Mon","timezone":"Europe/Berlin","announce_milestones":false,"announce_records":"server","mention_retention_days":null,"keywords":{"include":[{"pattern":"rust","mode":"whole_word"}],"exclude":[]},"regions":{"plain":true,"quotes":false,"code_blocks":false,"inline_code":true,"spoilers":true,"links":false},"normalization":{"nfkc":true,"strip_invisible":true,"confusables":true,"collapse_spacing":false,"leetspeak":false},"sources":{"text":{"enabled":true,"weight":2},"emoji":{"enabled":true,"weight":1},"stickers":{"enabled":true,"weight":1},"reactions":{"enabled":true,"weight":1},"names":["rust","ferris"],"ids":[],"unicode_emoji":["🦀"]},"deletion_grace_seconds":30,"ignore":{"bots":true,"webhooks":true,"system_messages":true,"users":[],"roles":[]},"channels":{"include":[],"exclude":[]}}"#;
+    {
+        let connection = rusqlite::Connection::open(&path).unwrap();
+        connection
+            .execute(
+                "INSERT INTO guild_settings (guild_id, settings) VALUES (?1, ?2)",
+                rusqlite::params![GUILD_ID.0 as i64, legacy],
+            )
+            .unwrap();
+        connection.pragma_update(None, "user_version", 3).unwrap();
+    }
+
+    let storage = SqliteStorage::open(&path).unwrap();
+    let overrides = serde_json::Value::Object(storage.load_setting_overrides(GUILD_ID).unwrap());
+    assert_eq!(
+        overrides,
+        json!({
+            "timezone": "Europe/Berlin",
+            "announce_milestones": false,
+            "sources": {
+                "text": { "enabled": true, "weight": 2 },
+                "emoji": { "enabled": true, "weight": 1 },
+                "stickers": { "enabled": true, "weight": 1 },
+                "reactions": { "enabled": true, "weight": 1 },
+                "names": ["rust", "ferris"],
+                "ids": [],
+                "unicode_emoji": ["🦀"],
+            },
+        })
+    );
+
+    drop(storage);
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn the_example_config_file_is_valid() {
+    Config::parse(include_str!("../crabe.example.toml")).unwrap();
+}
@@ -0,0 +1,95 @@
+mod fake_discord;
+
+use std::sync::Arc;
+
+use chrono::{Duration, TimeZone, Utc};
+use crabe_core::clock::MockClock;
+use crabe_de_la_crabe::storage::SqliteStorage;
+use fake_discord::{eventually, mention_count, Author, FakeDiscord};
+use serde_json::{json, Value};
+use serenity::model::Permissions;
+
+const CHANNEL_ID: u64 = 20;
+const CAR_CHANNEL_ID: u64 = 21;
+
+const FERRIS: Author = Author {
+    id: 100,
+    name: "ferris",
+    bot: false,
+};
+
+/// The data of `/since`, showing the record of a channel if one is given.
+fn since(channel_id: Option<u64>) -> Value {
+    match channel_id {
+        Some(channel_id) => json!({
+            "name": "since",
+            "options": [{ "name": "channel", "type": 7, "value": channel_id.to_string() }],
+            "resolved": {
+                "channels": {
+                    channel_id.to_string(): {
+                        "id": channel_id.to_string(),
+                        "name": "cars",
+                        "type": 0,
+                        "permissions": "0",
+                    },
+                },
+            },
+        }),
+        None => json!({ "name": "since" }),
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn since_shows_the_record_of_the_server_or_a_channel() {
+    let discord = FakeDiscord::start().await;
+    let start = Utc.with_ymd_and_hms(2023, 1, 2, 9, 0, 0).unwrap();
+    let clock = Arc::new(MockClock::new(start));
+    let storage = Arc::new(SqliteStorage::open(":memory:").unwrap());
+    discord.start_bot(storage.clone(), clock.clone()).await;
+
+    // The car channel goes a day without mentioning Rust and the other channel three days, while
+    // the server as a whole goes two days at most.
+    let mentions = [
+        (CAR_CHANNEL_ID, start),
+        (CHANNEL_ID, start),
+        (CAR_CHANNEL_ID, start + Duration::days(1)),
+        (CHANNEL_ID, start + Duration::days(3)),
+    ];
+    for (count, &(channel_id, sent_at)) in mentions.iter().enumerate() {
+        clock.set(sent_at);
+        discord.send_message(channel_id, &FERRIS, "Rust!", sent_at);
+        eventually("the mention", || {
+            mention_count(storage.as_ref(), &FERRIS) == count + 1
+        })
+        .await;
+    }
+    clock.set(start + Duration::days(4));
+
+    let interaction = discord.use_command(
+        CHANNEL_ID,
+        &FERRIS,
+        Permissions::SEND_MESSAGES,
+        &[],
+        since(Some(CAR_CHANNEL_ID)),
+    );
+    let response = discord.wait_for_response(interaction).await;
+    assert_eq!(
+        response["embeds"][0]["description"],
+        "It has been 3 days since somebody last mentioned Rust in <#21>.\n\n\
+         The record in <#21> is 1 day. You are setting a new record right now!"
+    );
+
+    let interaction = discord.use_command(
+        CHANNEL_ID,
+        &FERRIS,
+        Permissions::SEND_MESSAGES,
+        &[],
+        since(None),
+    );
+    let response = discord.wait_for_response(interaction).await;
+    assert_eq!(
+        response["embeds"][0]["description"],
+        "It has been 1 day since somebody last mentioned Rust on this server.\n\n\
+         The record on this server is 2 days. Hold out for another 1 day to beat it!"
+    );
+}
@@ -0,0 +1,248 @@
+mod fake_discord;
+
+use std::{sync::Arc, time::Duration as StdDuration};
+
+use chrono::{Duration, TimeZone, Utc};
+use crabe_core::{clock::MockClock, sources::MentionSource};
+use crabe_de_la_crabe::storage::{SqliteStorage, Storage};
+use fake_discord::{eventually, mention_count, Author, FakeDiscord, GUILD_ID};
+use serde_json::{json, Value};
+use serenity::model::prelude::GuildId;
+
+const CHANNEL_ID: u64 = 20;
+
+const FERRIS: Author = Author {
+    id: 100,
+    name: "ferris",
+    bot: false,
+};
+const CORRO: Author = Author {
+    id: 102,
+    name: "corro",
+    bot: false,
+};
+
+/// A message holding a single sticker.
+fn sticker(id: u64, name: &str) -> Value {
+    json!({ "sticker_items": [{ "id": id.to_string(), "name": name, "format_type": 1 }] })
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn a_custom_emoji_only_counts_as_an_emoji() {
+    let discord = FakeDiscord::start().await;
+    let start = Utc.with_ymd_and_hms(2023, 1, 2, 9, 0, 0).unwrap();
+    let clock = Arc::new(MockClock::new(start));
+    let storage = Arc::new(SqliteStorage::open(":memory:").unwrap());
+    discord.start_bot(storage.clone(), clock).await;
+
+    discord.send_message(CHANNEL_ID, &FERRIS, "Look <:rust:123>", start);
+    eventually("the mention", || {
+        mention_count(storage.as_ref(), &FERRIS) == 1
+    })
+    .await;
+
+    let mentions = storage.load_mentions(GuildId(GUILD_ID)).unwrap();
+    assert_eq!(mentions.len(), 1);
+    assert_eq!(mentions[0].source, MentionSource::Emoji);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn custom_emoji_dont_count_with_the_emoji_source_disabled() {
+    let discord = FakeDiscord::start().await;
+    let start = Utc.with_ymd_and_hms(2023, 1, 2, 9, 0, 0).unwrap();
+    let clock = Arc::new(MockClock::new(start));
+    let storage = Arc::new(SqliteStorage::open(":memory:").unwrap());
+    let overrides = json!({ "sources": { "emoji": { "enabled": false } } });
+    storage
+        .save_setting_overrides(GuildId(GUILD_ID), overrides.as_object().unwrap())
+        .unwrap();
+    discord.start_bot(storage.clone(), clock).await;
+
+    discord.send_message(CHANNEL_ID, &FERRIS, "Look <:rust:123>", start);
+    discord.send_message(CHANNEL_ID, &CORRO, "Rust!", start + Duration::hours(1));
+    eventually("the text mention", || {
+        mention_count(storage.as_ref(), &CORRO) == 1
+    })
+    .await;
+
+    assert_eq!(mention_count(storage.as_ref(), &FERRIS), 0);
+    assert_eq!(storage.load_mentions(GuildId(GUILD_ID)).unwrap().len(), 1);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn reactions_count_when_they_are_received() {
+    let discord = FakeDiscord::start().await;
+    let start = Utc.with_ymd_and_hms(2023, 1, 2, 9, 0, 0).unwrap();
+    let now = start + Duration::days(3);
+    let clock = Arc::new(MockClock::new(now));
+    let storage = Arc::new(SqliteStorage::open(":memory:").unwrap());
+    discord.start_bot(storage.clone(), clock).await;
+
+    let message = discord.send_message(CHANNEL_ID, &FERRIS, "Rust!", start);
+    eventually("the mention", || {
+        mention_count(storage.as_ref(), &FERRIS) == 1
+    })
+    .await;
+
+    discord.react(
+        CHANNEL_ID,
+        message,
+        &CORRO,
+        &[],
+        json!({ "id": null, "name": "👍" }),
+    );
+    discord.react(
+        CHANNEL_ID,
+        message,
+        &CORRO,
+        &[],
+        json!({ "id": null, "name": "🦀" }),
+    );
+    eventually("the reaction", || {
+        mention_count(storage.as_ref(), &CORRO) == 1
+    })
+    .await;
+    discord.react(
+        CHANNEL_ID,
+        message,
+        &FERRIS,
+        &[],
+        json!({ "id": "123", "name": "ferris_happy" }),
+    );
+    eventually("the custom reaction", || {
+        mention_count(storage.as_ref(), &FERRIS) == 2
+    })
+    .await;
+
+    let mentions = storage.load_mentions(GuildId(GUILD_ID)).unwrap();
+    assert_eq!(mentions.len(), 3);
+    assert_eq!(mentions[1].source, MentionSource::Reaction);
+    assert_eq!(mentions[1].message_id.0, message);
+    assert_eq!(mentions[1].rule.as_deref(), Some("🦀"));
+    // Reactions have no timestamp, so the time they were received is used instead.
+    assert_eq!(mentions[1].sent_at, now);
+    assert_eq!(mentions[2].rule.as_deref(), Some("<:ferris_happy:123>"));
+
+    let record = storage.load_records().unwrap()[&GuildId(GUILD_ID)].clone();
+    assert_eq!(
+        record.duration,
+        Some(StdDuration::from_secs(3 * 24 * 60 * 60))
+    );
+    assert_eq!(record.last_mention, Some(now));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn stickers_count_as_a_mention() {
+    let discord = FakeDiscord::start().await;
+    let start = Utc.with_ymd_and_hms(2023, 1, 2, 9, 0, 0).unwrap();
+    let clock = Arc::new(MockClock::new(start));
+    let storage = Arc::new(SqliteStorage::open(":memory:").unwrap());
+    discord.start_bot(storage.clone(), clock).await;
+
+    discord.send_message_with(CHANNEL_ID, &CORRO, "", start, sticker(5, "Wave"));
+    discord.send_message_with(
+        CHANNEL_ID,
+        &FERRIS,
+        "Hello",
+        start + Duration::hours(1),
+        sticker(6, "Ferris wave"),
+    );
+    eventually("the sticker", || {
+        mention_count(storage.as_ref(), &FERRIS) == 1
+    })
+    .await;
+
+    assert_eq!(mention_count(storage.as_ref(), &CORRO), 0);
+    let mentions = storage.load_mentions(GuildId(GUILD_ID)).unwrap();
+    assert_eq!(mentions.len(), 1);
+    assert_eq!(mentions[0].source, MentionSource::Sticker);
+    assert_eq!(mentions[0].rule.as_deref(), Some("Ferris wave"));
+    assert_eq!(mentions[0].sent_at, start + Duration::hours(1));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn each_source_counts_with_its_own_weight() {
+    let discord = FakeDiscord::start().await;
+    let start = Utc.with_ymd_and_hms(2023, 1, 2, 9, 0, 0).unwrap();
+    let clock = Arc::new(MockClock::new(start + Duration::hours(1)));
+    let storage = Arc::new(SqliteStorage::open(":memory:").unwrap());
+    let overrides = json!({
+        "sources": {
+            "emoji": { "weight": 2 },
+            "stickers": { "weight": 3 },
+            "reactions": { "weight": 5 },
+        },
+    });
+    storage
+        .save_setting_overrides(GuildId(GUILD_ID), overrides.as_object().unwrap())
+        .unwrap();
+    discord.start_bot(storage.clone(), clock).await;
+
+    let message =
+        discord.send_message_with(CHANNEL_ID, &FERRIS, "Rust! 🦀", start, sticker(6, "Ferris"));
+    eventually("the message", || {
+        mention_count(storage.as_ref(), &FERRIS) == 6
+    })
+    .await;
+    discord.react(
+        CHANNEL_ID,
+        message,
+        &FERRIS,
+        &[],
+        json!({ "id": null, "name": "🦀" }),
+    );
+    eventually("the reaction", || {
+        mention_count(storage.as_ref(), &FERRIS) == 11
+    })
+    .await;
+
+    let mentions = storage.load_mentions(GuildId(GUILD_ID)).unwrap();
+    let weights = mentions
+        .iter()
+        .map(|mention| (mention.source, mention.weight))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        weights,
+        [
+            (MentionSource::Text, 1),
+            (MentionSource::Emoji, 2),
+            (MentionSource::Sticker, 3),
+            (MentionSource::Reaction, 5),
+        ]
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn reactions_and_stickers_dont_count_with_their_source_disabled() {
+    let discord = FakeDiscord::start().await;
+    let start = Utc.with_ymd_and_hms(2023, 1, 2, 9, 0, 0).unwrap();
+    let clock = Arc::new(MockClock::new(start));
+    let storage = Arc::new(SqliteStorage::open(":memory:").unwrap());
+    let overrides = json!({
+        "sources": {
+            "stickers": { "enabled": false },
+            "reactions": { "enabled": false },
+        },
+    });
+    storage
+        .save_setting_overrides(GuildId(GUILD_ID), overrides.as_object().unwrap())
+        .unwrap();
+    discord.start_bot(storage.clone(), clock).await;
+
+    let message = discord.send_message_with(CHANNEL_ID, &FERRIS, "", start, sticker(6, "Ferris"));
+    discord.react(
+        CHANNEL_ID,
+        message,
+        &FERRIS,
+        &[],
+        json!({ "id": null, "name": "🦀" }),
+    );
+    discord.send_message(CHANNEL_ID, &CORRO, "Rust!", start + Duration::hours(1));
+    eventually("the text mention", || {
+        mention_count(storage.as_ref(), &CORRO) == 1
+    })
+    .await;
+
+    assert_eq!(mention_count(storage.as_ref(), &FERRIS), 0);
+    assert_eq!(storage.load_mentions(GuildId(GUILD_ID)).unwrap().len(), 1);
+}
@@ -0,0 +1,149 @@
+use std::{collections::HashMap, sync::Arc, time::Duration as StdDuration};
+
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use crabe_core::{clock::MockClock, record::Record, sources::MentionSource};
+use crabe_de_la_crabe::{
+    state::{GuildStates, Mention},
+    storage::{SqliteStorage, Storage},
+};
+use serenity::model::prelude::{ChannelId, GuildId, MessageId, UserId};
+
+const GUILD_ID: GuildId = GuildId(10);
+const CHANNEL_ID: ChannelId = ChannelId(20);
+const OTHER_CHANNEL_ID: ChannelId = ChannelId(21);
+
+const DAY: u64 = 24 * 60 * 60;
+
+fn mention(message_id: u64, user_id: u64, sent_at: DateTime<Utc>) -> Mention {
+    Mention {
+        guild_id: GUILD_ID,
+        channel_id: CHANNEL_ID,
+        user_id: UserId(user_id),
+        message_id: MessageId(message_id),
+        source: MentionSource::Text,
+        weight: 1,
+        sent_at,
+        rule: Some("rust".to_string()),
+    }
+}
+
+/// The counts and records of the guild, in the whole guild and in each channel.
+async fn snapshot(states: &GuildStates) -> (HashMap<UserId, usize>, Record, Record, Record) {
+    (
+        states.mention_counts(GUILD_ID).await,
+        states.record(GUILD_ID, None).await,
+        states.record(GUILD_ID, Some(CHANNEL_ID)).await,
+        states.record(GUILD_ID, Some(OTHER_CHANNEL_ID)).await,
+    )
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn concurrent_requests_for_a_guild_are_handled_one_at_a_time() {
+    let start = Utc.with_ymd_and_hms(2023, 1, 2, 9, 0, 0).unwrap();
+    let later = start + Duration::days(1);
+    let clock = Arc::new(MockClock::new(later));
+    let storage = Arc::new(SqliteStorage::open(":memory:").unwrap());
+    let states = Arc::new(GuildStates::load(storage.clone(), clock).unwrap());
+
+    // The first mention starts the streak, and the one of the user 2 is taken back later on.
+    states.track(vec![mention(1, 1, start)], false).await;
+    states.track(vec![mention(2, 2, later)], true).await;
+
+    // Every user from 100 on mentions Rust 5 times, all at once, ending the same streak.
+    let mut tasks = Vec::new();
+    for user_id in 100..120 {
+        for message in 0..5 {
+            let states = states.clone();
+            let message_id = 1000 + user_id * 10 + message;
+            tasks.push(tokio::spawn(async move {
+                states
+                    .track(vec![mention(message_id, user_id, later)], false)
+                    .await
+            }));
+        }
+    }
+    let reverted = {
+        let states = states.clone();
+        tokio::spawn(async move {
+            states
+                .revert(GUILD_ID, vec![MessageId(2)], later - Duration::minutes(1))
+                .await
+        })
+    };
+    let corrected = {
+        let states = states.clone();
+        tokio::spawn(async move { states.set_mention_count(GUILD_ID, UserId(3), 42).await })
+    };
+
+    let mut beaten = Vec::new();
+    for task in tasks {
+        let tracked = task.await.unwrap().unwrap();
+        beaten.extend(tracked.beaten);
+    }
+    assert_eq!(reverted.await.unwrap(), 1);
+    assert_eq!(corrected.await.unwrap(), 0);
+
+    let mut counts = states.mention_counts(GUILD_ID).await;
+    assert_eq!(counts.get(&UserId(1)), Some(&1));
+    assert_eq!(counts.get(&UserId(2)).copied().unwrap_or_default(), 0);
+    assert_eq!(counts.get(&UserId(3)), Some(&42));
+    for user_id in 100..120 {
+        assert_eq!(counts.get(&UserId(user_id)), Some(&5));
+    }
+    let mut saved = storage.load_mention_counts().unwrap()[&GUILD_ID].clone();
+    saved.retain(|_, count| *count > 0);
+    counts.retain(|_, count| *count > 0);
+    assert_eq!(saved, counts);
+
+    // Either the reverted mention or one of the others ended the streak, but only once.
+    assert!(beaten.len() <= 1);
+    let record = states.record(GUILD_ID, None).await;
+    assert_eq!(record.duration, Some(StdDuration::from_secs(DAY)));
+    assert_eq!(record.last_mention, Some(later));
+    assert_eq!(storage.load_records().unwrap()[&GUILD_ID], record);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn the_state_is_the_same_after_a_restart() {
+    let path = std::env::temp_dir().join(format!("crabe-state-{}.sqlite3", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let start = Utc.with_ymd_and_hms(2023, 1, 2, 9, 0, 0).unwrap();
+    let clock = Arc::new(MockClock::new(start + Duration::days(10)));
+
+    let before = {
+        let storage = Arc::new(SqliteStorage::open(&path).unwrap());
+        let states = GuildStates::load(storage, clock.clone()).unwrap();
+        states.track(vec![mention(1, 1, start)], false).await;
+        states
+            .track(vec![mention(2, 2, start + Duration::days(3))], false)
+            .await;
+        let mut other = mention(3, 1, start + Duration::days(4));
+        other.channel_id = OTHER_CHANNEL_ID;
+        states.track(vec![other], false).await;
+        states
+            .track(vec![mention(4, 3, start + Duration::days(5))], false)
+            .await;
+        states.set_mention_count(GUILD_ID, UserId(2), 7).await;
+        states
+            .set_record(
+                GUILD_ID,
+                Some(OTHER_CHANNEL_ID),
+                Some(StdDuration::from_secs(2 * DAY)),
+            )
+            .await;
+        snapshot(&states).await
+    };
+    assert_eq!(before.0[&UserId(1)], 2);
+    assert_eq!(before.1.duration, Some(StdDuration::from_secs(3 * DAY)));
+
+    let storage = Arc::new(SqliteStorage::open(&path).unwrap());
+    let states = GuildStates::load(storage, clock).unwrap();
+    assert_eq!(snapshot(&states).await, before);
+
+    // Replaying the log on the baseline arrives at the same state as well.
+    assert_eq!(states.rebuild(GUILD_ID, None).await, Some(0));
+    assert_eq!(snapshot(&states).await, before);
+
+    drop(states);
+    let _ = std::fs::remove_file(&path);
+}